@@ -0,0 +1,55 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{Store, StoreResult};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// An append-only log of values, e.g. of events, stored under consecutive sequence
+/// numbers starting from 0.
+///
+/// The sequence numbers are assigned by the background task of the store, which serves
+/// the appends one at a time: concurrent appends, from any clone of the log, are given
+/// distinct and increasing sequence numbers. The store must only be written through the
+/// log, for its keys to stay contiguous.
+pub struct AppendOnlyLog<V> {
+    store: Store<u64, V>,
+}
+
+// not derived, which would require the values to be Clone
+impl<V> Clone for AppendOnlyLog<V> {
+    fn clone(&self) -> Self {
+        AppendOnlyLog {
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<V> AppendOnlyLog<V>
+where
+    V: Serialize + DeserializeOwned + Send + Clone + 'static,
+{
+    pub fn new(store: Store<u64, V>) -> Self {
+        AppendOnlyLog { store }
+    }
+
+    /// Appends the value at the end of the log, and returns its sequence number.
+    pub async fn append(&self, value: V) -> StoreResult<u64> {
+        self.store
+            .append(value, |last| last.map_or(0, |sequence| sequence + 1))
+            .await
+    }
+
+    /// Returns at most `limit` consecutive entries of the log, starting from the sequence
+    /// number `sequence`, to tail the log from the last sequence number read.
+    pub async fn read_from(&self, sequence: u64, limit: usize) -> StoreResult<Vec<(u64, V)>> {
+        let end = sequence.saturating_add(limit as u64);
+        if end == sequence {
+            return Ok(Vec::new());
+        }
+        self.store.read_range_ordered(sequence, end).await
+    }
+
+    /// The store behind this log.
+    pub fn store(&self) -> &Store<u64, V> {
+        &self.store
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{Store, StoreResult};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::runtime::Handle;
+
+/// A synchronous facade over a `Store`, for code paths that don't run in an async
+/// context (e.g. a thread pool worker). Each call blocks the current thread on the
+/// corresponding async method, through the given runtime handle.
+///
+/// The methods must not be called from within an async task: blocking a runtime thread
+/// on the store can deadlock, if the store's background task needs that very thread to
+/// make progress. Tokio panics when this is attempted.
+pub struct BlockingStore<K, V> {
+    store: Store<K, V>,
+    handle: Handle,
+}
+
+// not derived, which would require the keys and values to be Clone
+impl<K, V> Clone for BlockingStore<K, V> {
+    fn clone(&self) -> Self {
+        BlockingStore {
+            store: self.store.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<Key, Value> BlockingStore<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send,
+    Value: Serialize + DeserializeOwned + Send,
+{
+    pub fn new(store: Store<Key, Value>, handle: Handle) -> Self {
+        BlockingStore { store, handle }
+    }
+
+    pub fn write(&self, key: Key, value: Value) {
+        self.handle.block_on(self.store.write(key, value))
+    }
+
+    /// Atomically writes all the key-value pairs in storage.
+    pub fn write_all(
+        &self,
+        key_value_pairs: impl IntoIterator<Item = (Key, Value)>,
+    ) -> StoreResult<()> {
+        self.handle.block_on(self.store.write_all(key_value_pairs))
+    }
+
+    pub fn remove(&self, key: Key) {
+        self.handle.block_on(self.store.remove(key))
+    }
+
+    /// Atomically removes all the data referenced by the provided keys.
+    pub fn remove_all(&self, keys: impl IntoIterator<Item = Key>) -> StoreResult<()> {
+        self.handle.block_on(self.store.remove_all(keys))
+    }
+
+    pub fn read(&self, key: Key) -> StoreResult<Option<Value>> {
+        self.handle.block_on(self.store.read(key))
+    }
+
+    /// Fetches all the values for the provided keys.
+    pub fn read_all(&self, keys: impl IntoIterator<Item = Key>) -> StoreResult<Vec<Option<Value>>> {
+        self.handle.block_on(self.store.read_all(keys))
+    }
+
+    /// Blocks until a value is written for the key, see `Store::notify_read`.
+    pub fn notify_read(&self, key: Key) -> StoreResult<Option<Value>> {
+        self.handle.block_on(self.store.notify_read(key))
+    }
+
+    /// The async store behind this facade.
+    pub fn store(&self) -> &Store<Key, Value> {
+        &self.store
+    }
+}
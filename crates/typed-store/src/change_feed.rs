@@ -0,0 +1,313 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::rocks::{be_fix_int_ser, TypedStoreError};
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{marker::PhantomData, sync::Arc};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+/// The number of changes buffered for the slowest subscriber of a change feed, past
+/// which it lags.
+pub const CHANGE_FEED_CAPACITY: usize = 1024;
+
+/// A mutation applied by a `Store`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<K, V> {
+    Write(K, V),
+    Delete(K),
+    /// the deletion of all the keys whose serialized form starts with these bytes
+    DeletePrefix(Vec<u8>),
+    /// mutations applied atomically, by a single command
+    Batch(Vec<Change<K, V>>),
+}
+
+/// A change as it is carried by the feed, with its keys and values serialized as they
+/// are stored. The channel of the feed is then the same whatever the types of the store,
+/// which spares requiring them to be Clone or Sync.
+pub(crate) type RawChange = Change<Vec<u8>, Vec<u8>>;
+
+/// The error of receiving from a `ChangeFeed` or a `KeyWatch`.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum RecvError {
+    #[error("the store is closed")]
+    Closed,
+    #[error("the subscriber lagged behind, missing {0} changes")]
+    Lagged(u64),
+    #[error("the change failed to deserialize: {0}")]
+    Deserialization(TypedStoreError),
+}
+
+impl From<broadcast::error::RecvError> for RecvError {
+    fn from(err: broadcast::error::RecvError) -> Self {
+        match err {
+            broadcast::error::RecvError::Closed => RecvError::Closed,
+            broadcast::error::RecvError::Lagged(missed) => RecvError::Lagged(missed),
+        }
+    }
+}
+
+/// The feed of all the mutations applied by a `Store`, in the order they are applied,
+/// obtained with `Store::change_feed`. Only the mutations applied after subscribing
+/// are received.
+///
+/// The feed is bounded: the store never waits on its subscribers, and a subscriber
+/// falling behind by more than `CHANGE_FEED_CAPACITY` changes misses the oldest of them,
+/// which `recv` reports with `RecvError::Lagged` before carrying on with the changes
+/// still buffered. Each change is therefore received at most once.
+///
+/// With the `futures-core` feature, `into_stream` turns the feed into a `Stream`.
+pub struct ChangeFeed<K, V> {
+    receiver: broadcast::Receiver<Arc<RawChange>>,
+    _phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> ChangeFeed<K, V> {
+    pub(crate) fn new(receiver: broadcast::Receiver<Arc<RawChange>>) -> Self {
+        ChangeFeed {
+            receiver,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned> ChangeFeed<K, V> {
+    /// Waits for the next change. Fails with `RecvError::Closed` once the store is
+    /// closed and all the changes are received.
+    pub async fn recv(&mut self) -> Result<Change<K, V>, RecvError> {
+        let change = self.receiver.recv().await?;
+        decode(&change).map_err(RecvError::Deserialization)
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<K, V> ChangeFeed<K, V>
+where
+    K: DeserializeOwned + Send + 'static,
+    V: DeserializeOwned + Send + 'static,
+{
+    /// Turns the feed into a stream of the results of `recv`, which ends once the store
+    /// is closed and all the changes are received. A lagging subscriber receives an
+    /// `RecvError::Lagged` item, and carries on.
+    pub fn into_stream(self) -> ChangeStream<K, V> {
+        ChangeStream {
+            next: Box::pin(next_change(self)),
+        }
+    }
+}
+
+/// A `ChangeFeed` as a `Stream`, obtained with `ChangeFeed::into_stream`.
+#[cfg(feature = "futures-core")]
+pub struct ChangeStream<K, V> {
+    /// the `recv` in progress, which hands the feed back along with its result
+    next: std::pin::Pin<Box<dyn std::future::Future<Output = NextChange<K, V>> + Send>>,
+}
+
+#[cfg(feature = "futures-core")]
+type NextChange<K, V> = (Result<Change<K, V>, RecvError>, ChangeFeed<K, V>);
+
+#[cfg(feature = "futures-core")]
+async fn next_change<K, V>(mut feed: ChangeFeed<K, V>) -> NextChange<K, V>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    let change = feed.recv().await;
+    (change, feed)
+}
+
+#[cfg(feature = "futures-core")]
+impl<K, V> futures_core::Stream for ChangeStream<K, V>
+where
+    K: DeserializeOwned + Send + 'static,
+    V: DeserializeOwned + Send + 'static,
+{
+    type Item = Result<Change<K, V>, RecvError>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let (change, feed) =
+            futures_core::ready!(std::future::Future::poll(self.next.as_mut(), cx));
+        self.next = Box::pin(next_change(feed));
+        match change {
+            Err(RecvError::Closed) => std::task::Poll::Ready(None),
+            change => std::task::Poll::Ready(Some(change)),
+        }
+    }
+}
+
+/// The updates of a single key, obtained with `Store::get_and_watch`: the values the key
+/// takes after the read returned with the watch, `None` for its removals.
+///
+/// The watch filters a change feed, so it lags like one: past `CHANGE_FEED_CAPACITY`
+/// changes of the store (of any key) behind, `recv` reports `RecvError::Lagged`, and the
+/// state of the key must then be read again. The removal of a prefix applies to the key
+/// whether it was present or not, so an update may repeat the previous value.
+///
+/// With the `futures-core` feature, `into_stream` turns the watch into a `Stream`.
+pub struct KeyWatch<K, V> {
+    key: Vec<u8>,
+    receiver: broadcast::Receiver<Arc<RawChange>>,
+    _phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> KeyWatch<K, V> {
+    pub(crate) fn new(key: Vec<u8>, receiver: broadcast::Receiver<Arc<RawChange>>) -> Self {
+        KeyWatch {
+            key,
+            receiver,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The serialized value the change leaves under the key, if it touches the key.
+    fn update<'c>(&self, change: &'c RawChange) -> Option<Option<&'c [u8]>> {
+        match change {
+            Change::Write(key, value) if *key == self.key => Some(Some(value.as_slice())),
+            Change::Delete(key) if *key == self.key => Some(None),
+            Change::DeletePrefix(prefix) if self.key.starts_with(prefix) => Some(None),
+            Change::Batch(changes) => changes.iter().filter_map(|c| self.update(c)).last(),
+            _ => None,
+        }
+    }
+}
+
+impl<K, V: DeserializeOwned> KeyWatch<K, V> {
+    /// Waits for the next update of the key. Fails with `RecvError::Closed` once the
+    /// store is closed and all the updates are received.
+    pub async fn recv(&mut self) -> Result<Option<V>, RecvError> {
+        loop {
+            let change = self.receiver.recv().await?;
+            if let Some(update) = self.update(&change) {
+                return update
+                    .map(bincode::deserialize)
+                    .transpose()
+                    .map_err(|e| RecvError::Deserialization(e.into()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<K, V> KeyWatch<K, V>
+where
+    K: 'static,
+    V: DeserializeOwned + Send + 'static,
+{
+    /// Turns the watch into a stream of the results of `recv`, which ends once the store
+    /// is closed and all the updates are received. A lagging subscriber receives an
+    /// `RecvError::Lagged` item, and carries on.
+    pub fn into_stream(self) -> KeyWatchStream<K, V> {
+        KeyWatchStream {
+            next: Box::pin(next_update(self)),
+        }
+    }
+}
+
+/// A `KeyWatch` as a `Stream`, obtained with `KeyWatch::into_stream`.
+#[cfg(feature = "futures-core")]
+pub struct KeyWatchStream<K, V> {
+    /// the `recv` in progress, which hands the watch back along with its result
+    next: std::pin::Pin<Box<dyn std::future::Future<Output = NextUpdate<K, V>> + Send>>,
+}
+
+#[cfg(feature = "futures-core")]
+type NextUpdate<K, V> = (Result<Option<V>, RecvError>, KeyWatch<K, V>);
+
+#[cfg(feature = "futures-core")]
+async fn next_update<K, V: DeserializeOwned>(mut watch: KeyWatch<K, V>) -> NextUpdate<K, V> {
+    let update = watch.recv().await;
+    (update, watch)
+}
+
+#[cfg(feature = "futures-core")]
+impl<K, V> futures_core::Stream for KeyWatchStream<K, V>
+where
+    K: 'static,
+    V: DeserializeOwned + Send + 'static,
+{
+    type Item = Result<Option<V>, RecvError>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let (update, watch) =
+            futures_core::ready!(std::future::Future::poll(self.next.as_mut(), cx));
+        self.next = Box::pin(next_update(watch));
+        match update {
+            Err(RecvError::Closed) => std::task::Poll::Ready(None),
+            update => std::task::Poll::Ready(Some(update)),
+        }
+    }
+}
+
+/// The sending half of the change feed of a store.
+pub(crate) struct Publisher<K, V> {
+    changes: broadcast::Sender<Arc<RawChange>>,
+    _phantom: PhantomData<fn(K, V)>,
+}
+
+impl<K: Serialize, V: Serialize> Publisher<K, V> {
+    pub(crate) fn new(changes: broadcast::Sender<Arc<RawChange>>) -> Self {
+        Publisher {
+            changes,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Publishes the change to the subscribers of the feed, if there are any: the change
+    /// is only built, and serialized, then.
+    pub(crate) fn publish(&self, change: impl FnOnce() -> Option<Change<K, V>>) {
+        if self.changes.receiver_count() > 0 {
+            if let Some(change) = change() {
+                match encode(&change) {
+                    Ok(change) => {
+                        let _ = self.changes.send(Arc::new(change));
+                    }
+                    // the entries were just stored, so they do serialize
+                    Err(e) => tracing::warn!("Failed to publish a change of the store: {e}"),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Arc<RawChange>> {
+        self.changes.subscribe()
+    }
+}
+
+fn encode<K: Serialize, V: Serialize>(change: &Change<K, V>) -> Result<RawChange, TypedStoreError> {
+    let raw = match change {
+        Change::Write(key, value) => {
+            Change::Write(be_fix_int_ser(key)?, bincode::serialize(value)?)
+        }
+        Change::Delete(key) => Change::Delete(be_fix_int_ser(key)?),
+        Change::DeletePrefix(prefix) => Change::DeletePrefix(prefix.clone()),
+        Change::Batch(changes) => {
+            Change::Batch(changes.iter().map(encode).collect::<Result<_, _>>()?)
+        }
+    };
+    Ok(raw)
+}
+
+fn decode<K: DeserializeOwned, V: DeserializeOwned>(
+    change: &RawChange,
+) -> Result<Change<K, V>, TypedStoreError> {
+    let config = bincode::DefaultOptions::new()
+        .with_big_endian()
+        .with_fixint_encoding();
+    let change = match change {
+        Change::Write(key, value) => {
+            Change::Write(config.deserialize(key)?, bincode::deserialize(value)?)
+        }
+        Change::Delete(key) => Change::Delete(config.deserialize(key)?),
+        Change::DeletePrefix(prefix) => Change::DeletePrefix(prefix.clone()),
+        Change::Batch(changes) => {
+            Change::Batch(changes.iter().map(decode).collect::<Result<_, _>>()?)
+        }
+    };
+    Ok(change)
+}
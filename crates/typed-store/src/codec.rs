@@ -0,0 +1,275 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{
+    rocks::{DBMap, TypedStoreError},
+    traits::Map,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A value tagged with the name of its concrete type, as produced by a [`DynCodec`].
+///
+/// A `DBMap<K, DynValue>` holds values of several concrete types in the same
+/// column family: the tag selects the deserializer to use when reading them back.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DynValue {
+    tag: String,
+    payload: Vec<u8>,
+}
+
+impl DynValue {
+    /// The tag of the concrete type this value was encoded from.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+}
+
+type Decoder<T> = Box<dyn Fn(&[u8]) -> Result<Box<T>, TypedStoreError> + Send + Sync>;
+
+/// A registry of type tag -> deserializer, used to store trait objects (e.g.
+/// `Box<dyn Plugin>`) whose concrete type is only known at runtime.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use typed_store::codec::DynCodec;
+///
+/// trait Plugin {
+///     fn name(&self) -> String;
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Echo;
+/// impl Plugin for Echo {
+///     fn name(&self) -> String {
+///         "echo".to_string()
+///     }
+/// }
+///
+/// let codec =
+///     DynCodec::<dyn Plugin>::new().register("echo", |e: Echo| -> Box<dyn Plugin> { Box::new(e) });
+/// let value = codec.encode("echo", &Echo).unwrap();
+/// assert_eq!(codec.decode(&value).unwrap().name(), "echo");
+/// ```
+pub struct DynCodec<T: ?Sized> {
+    decoders: HashMap<String, Decoder<T>>,
+}
+
+impl<T: ?Sized + 'static> Default for DynCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized + 'static> DynCodec<T> {
+    pub fn new() -> Self {
+        DynCodec {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Registers the concrete type `C` under `tag`. Decoded values are turned
+    /// into the dynamic type by `into_dyn`, which is usually `|c| Box::new(c)`.
+    pub fn register<C, F>(mut self, tag: impl Into<String>, into_dyn: F) -> Self
+    where
+        C: DeserializeOwned,
+        F: Fn(C) -> Box<T> + Send + Sync + 'static,
+    {
+        self.decoders.insert(
+            tag.into(),
+            Box::new(move |bytes: &[u8]| -> Result<Box<T>, TypedStoreError> {
+                Ok(into_dyn(bincode::deserialize(bytes)?))
+            }),
+        );
+        self
+    }
+
+    /// Encodes a value of a registered concrete type, tagging it with `tag`.
+    pub fn encode<C: Serialize>(&self, tag: &str, value: &C) -> Result<DynValue, TypedStoreError> {
+        if !self.decoders.contains_key(tag) {
+            return Err(unregistered_tag(tag));
+        }
+        Ok(DynValue {
+            tag: tag.to_string(),
+            payload: bincode::serialize(value)?,
+        })
+    }
+
+    /// Decodes a value with the deserializer registered for its tag.
+    pub fn decode(&self, value: &DynValue) -> Result<Box<T>, TypedStoreError> {
+        let decoder = self
+            .decoders
+            .get(&value.tag)
+            .ok_or_else(|| unregistered_tag(&value.tag))?;
+        decoder(&value.payload)
+    }
+
+    /// Encodes the value and inserts it in the map.
+    pub fn insert<K, C>(
+        &self,
+        map: &DBMap<K, DynValue>,
+        key: &K,
+        tag: &str,
+        value: &C,
+    ) -> Result<(), TypedStoreError>
+    where
+        K: Serialize + DeserializeOwned,
+        C: Serialize,
+    {
+        map.insert(key, &self.encode(tag, value)?)
+    }
+
+    /// Reads the value for the key from the map, and decodes it.
+    pub fn get<K>(
+        &self,
+        map: &DBMap<K, DynValue>,
+        key: &K,
+    ) -> Result<Option<Box<T>>, TypedStoreError>
+    where
+        K: Serialize + DeserializeOwned,
+    {
+        map.get(key)?.map(|value| self.decode(&value)).transpose()
+    }
+}
+
+/// A value prefixed with the version of the schema it was encoded with, as produced by a
+/// [`VersionedCodec`]. The version is the first byte of the stored value.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VersionedValue {
+    version: u8,
+    payload: Vec<u8>,
+}
+
+impl VersionedValue {
+    /// The schema version this value was encoded with.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+type VersionedDecoder<T> = Box<dyn Fn(&[u8]) -> Result<T, TypedStoreError> + Send + Sync>;
+
+/// A registry of schema version -> deserializer, so that values written before a schema
+/// change can still be read once the value type has evolved.
+///
+/// Values are always encoded with the current version. Each registered version decodes
+/// its own schema, and turns it into the current type through an up-migration hook,
+/// which is the identity for the current version itself.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use typed_store::codec::VersionedCodec;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct V1 {
+///     name: String,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct V2 {
+///     name: String,
+///     retries: u32,
+/// }
+///
+/// let old = VersionedCodec::<V1>::new(1).register(1, |v: V1| v);
+/// let value = old.encode(&V1 { name: "job".to_string() }).unwrap();
+///
+/// let codec = VersionedCodec::<V2>::new(2)
+///     .register(1, |v: V1| V2 { name: v.name, retries: 3 })
+///     .register(2, |v: V2| v);
+/// assert_eq!(codec.decode(&value).unwrap().retries, 3);
+/// ```
+pub struct VersionedCodec<T> {
+    current: u8,
+    decoders: HashMap<u8, VersionedDecoder<T>>,
+}
+
+impl<T: 'static> VersionedCodec<T> {
+    /// A codec encoding values with the `current` schema version, which must be
+    /// registered before values can be encoded.
+    pub fn new(current: u8) -> Self {
+        VersionedCodec {
+            current,
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// The version values are encoded with.
+    pub fn current_version(&self) -> u8 {
+        self.current
+    }
+
+    /// Registers the schema `C` of `version`. Decoded values are turned into the current
+    /// type by `upgrade`, which is `|v| v` for the current version.
+    pub fn register<C, F>(mut self, version: u8, upgrade: F) -> Self
+    where
+        C: DeserializeOwned,
+        F: Fn(C) -> T + Send + Sync + 'static,
+    {
+        self.decoders.insert(
+            version,
+            Box::new(move |bytes: &[u8]| -> Result<T, TypedStoreError> {
+                Ok(upgrade(bincode::deserialize(bytes)?))
+            }),
+        );
+        self
+    }
+
+    /// Encodes the value with the current version.
+    pub fn encode(&self, value: &T) -> Result<VersionedValue, TypedStoreError>
+    where
+        T: Serialize,
+    {
+        if !self.decoders.contains_key(&self.current) {
+            return Err(unregistered_version(self.current));
+        }
+        Ok(VersionedValue {
+            version: self.current,
+            payload: bincode::serialize(value)?,
+        })
+    }
+
+    /// Decodes a value with the deserializer registered for its version.
+    pub fn decode(&self, value: &VersionedValue) -> Result<T, TypedStoreError> {
+        let decoder = self
+            .decoders
+            .get(&value.version)
+            .ok_or_else(|| unregistered_version(value.version))?;
+        decoder(&value.payload)
+    }
+
+    /// Encodes the value and inserts it in the map.
+    pub fn insert<K>(
+        &self,
+        map: &DBMap<K, VersionedValue>,
+        key: &K,
+        value: &T,
+    ) -> Result<(), TypedStoreError>
+    where
+        K: Serialize + DeserializeOwned,
+        T: Serialize,
+    {
+        map.insert(key, &self.encode(value)?)
+    }
+
+    /// Reads the value for the key from the map, and decodes it.
+    pub fn get<K>(
+        &self,
+        map: &DBMap<K, VersionedValue>,
+        key: &K,
+    ) -> Result<Option<T>, TypedStoreError>
+    where
+        K: Serialize + DeserializeOwned,
+    {
+        map.get(key)?.map(|value| self.decode(&value)).transpose()
+    }
+}
+
+fn unregistered_version(version: u8) -> TypedStoreError {
+    TypedStoreError::SerializationError(format!(
+        "no decoder registered for schema version {version}"
+    ))
+}
+
+fn unregistered_tag(tag: &str) -> TypedStoreError {
+    TypedStoreError::SerializationError(format!("no decoder registered for type tag {tag}"))
+}
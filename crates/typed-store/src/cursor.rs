@@ -0,0 +1,83 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{rocks, StoreError, StoreResult};
+use serde::Serialize;
+use std::{fmt::Write, marker::PhantomData};
+
+/// A position in a paginated scan of a `Store`, see `Store::scan_prefix_cursor`: the next
+/// page starts at the first key strictly greater than the last key seen.
+///
+/// A cursor only holds the serialized form of the last key seen, and stays valid however
+/// the store is written in between pages. It converts to an opaque token, e.g. to hand it
+/// to the clients of a paginated API, from which the scan can be resumed later on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor<K> {
+    last: Option<Vec<u8>>,
+    _phantom: PhantomData<fn() -> K>,
+}
+
+impl<K> Default for Cursor<K> {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl<K> Cursor<K> {
+    /// A cursor at the start of the scan.
+    pub fn start() -> Self {
+        Cursor {
+            last: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// A cursor resuming the scan after `key`.
+    pub fn after(key: &K) -> StoreResult<Self>
+    where
+        K: Serialize,
+    {
+        Ok(Self::from_raw(Some(rocks::be_fix_int_ser(key)?)))
+    }
+
+    pub(crate) fn from_raw(last: Option<Vec<u8>>) -> Self {
+        Cursor {
+            last,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn into_raw(self) -> Option<Vec<u8>> {
+        self.last
+    }
+
+    /// Encodes the cursor into a token, from which `from_token` recreates it. The start of
+    /// the scan is the empty token.
+    pub fn to_token(&self) -> String {
+        let mut token = String::new();
+        for byte in self.last.iter().flatten() {
+            write!(token, "{byte:02x}").expect("writing to a String doesn't fail");
+        }
+        token
+    }
+
+    /// Decodes a cursor from a token returned by `to_token`.
+    pub fn from_token(token: &str) -> StoreResult<Self> {
+        if token.is_empty() {
+            return Ok(Self::start());
+        }
+        let invalid = || StoreError::SerializationError(format!("invalid cursor token {token}"));
+        if token.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        let last = (0..token.len())
+            .step_by(2)
+            .map(|i| {
+                token
+                    .get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                    .ok_or_else(invalid)
+            })
+            .collect::<StoreResult<Vec<u8>>>()?;
+        Ok(Self::from_raw(Some(last)))
+    }
+}
@@ -13,11 +13,24 @@ use serde::{de::DeserializeOwned, Serialize};
 use std::{
     cmp::Eq,
     collections::{HashMap, VecDeque},
+    future::Future,
     hash::Hash,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    task::{Context, Poll},
+    time::Duration,
 };
-use tokio::sync::{
-    mpsc::{channel, Sender},
-    oneshot,
+use tokio::{
+    sync::{
+        broadcast,
+        mpsc::{channel, error::TrySendError, Sender},
+        oneshot, OwnedSemaphorePermit, Semaphore, TryAcquireError,
+    },
+    time::Instant,
 };
 
 pub mod traits;
@@ -27,22 +40,249 @@ pub mod rocks;
 #[path = "tests/store_tests.rs"]
 pub mod store_tests;
 
+pub mod codec;
+#[cfg(test)]
+#[path = "tests/codec_tests.rs"]
+pub mod codec_tests;
+
+pub mod blocking;
+pub use blocking::BlockingStore;
+
+pub mod change_feed;
+pub use change_feed::{Change, ChangeFeed, KeyWatch};
+use change_feed::{Publisher, RawChange};
+
+pub mod metrics;
+pub use metrics::StoreMetrics;
+
+pub mod reader;
+pub use reader::StoreReader;
+
+pub mod append_log;
+pub use append_log::AppendOnlyLog;
+
+pub mod cursor;
+pub use cursor::Cursor;
+
+pub mod multi_store;
+pub use multi_store::MultiStore;
+#[cfg(test)]
+#[path = "tests/multi_store_tests.rs"]
+pub mod multi_store_tests;
+
+#[cfg(feature = "tower")]
+pub mod service;
+#[cfg(feature = "tower")]
+pub use service::{StoreRequest, StoreResponse};
+#[cfg(all(test, feature = "tower"))]
+#[path = "tests/service_tests.rs"]
+pub mod service_tests;
+
 pub type StoreError = rocks::TypedStoreError;
 type StoreResult<T> = Result<T, StoreError>;
 
 pub enum StoreCommand<Key, Value> {
     Write(Key, Value),
     WriteAll(Vec<(Key, Value)>, oneshot::Sender<StoreResult<()>>),
+    WriteAllIfAbsent(Vec<(Key, Value)>, oneshot::Sender<StoreResult<bool>>),
+    WriteIfChanged(Key, Value, oneshot::Sender<StoreResult<bool>>),
+    /// Writes the value under the key derived by the function from the last key of the
+    /// store, and replies with that key.
+    Append(
+        Value,
+        fn(Option<Key>) -> Key,
+        oneshot::Sender<StoreResult<Key>>,
+    ),
     Delete(Key),
     DeleteAll(Vec<Key>, oneshot::Sender<StoreResult<()>>),
+    DeletePrefix(Vec<u8>, oneshot::Sender<StoreResult<u64>>),
+    DeleteAllExisting(Vec<Key>, oneshot::Sender<StoreResult<Vec<Key>>>),
+    CompareAndDelete(Key, Value, oneshot::Sender<StoreResult<bool>>),
     Read(Key, oneshot::Sender<StoreResult<Option<Value>>>),
     ReadAll(Vec<Key>, oneshot::Sender<StoreResult<Vec<Option<Value>>>>),
+    ContainsAll(Vec<Key>, oneshot::Sender<StoreResult<Vec<bool>>>),
+    ReadWithDeadline(Key, Instant, oneshot::Sender<StoreResult<Option<Value>>>),
+    ReadAllWithDeadline(
+        Vec<Key>,
+        Instant,
+        oneshot::Sender<StoreResult<Vec<Option<Value>>>>,
+    ),
+    ScanPrefixPage(
+        Vec<u8>,
+        Option<Vec<u8>>,
+        usize,
+        oneshot::Sender<StoreResult<rocks::PrefixPage<Key, Value>>>,
+    ),
+    ReadRangeOrdered(Key, Key, oneshot::Sender<StoreResult<Vec<(Key, Value)>>>),
+    GetAndWatch(
+        Key,
+        Vec<u8>,
+        oneshot::Sender<StoreResult<WatchedValue<Key, Value>>>,
+    ),
+    Scan(
+        ScanOpts<Vec<u8>>,
+        oneshot::Sender<StoreResult<Vec<(Key, Value)>>>,
+    ),
     NotifyRead(Key, oneshot::Sender<StoreResult<Option<Value>>>),
+    /// Reads the key, or on a miss, claims its loading for the caller, unless another
+    /// caller is already loading it.
+    ReadOrClaim(Key, oneshot::Sender<StoreResult<Claim<Key, Value>>>),
+    /// Completes the loading of a key claimed by `ReadOrClaim`, writing the loaded value.
+    Populate(Key, StoreResult<Value>, oneshot::Sender<StoreResult<Value>>),
+    NotifyReadAny(Vec<Key>, oneshot::Sender<StoreResult<(Key, Value)>>),
+    /// Reads the database, to check that the task and the database are responsive.
+    Ping(oneshot::Sender<StoreResult<()>>),
+    Version(oneshot::Sender<StoreResult<u64>>),
+    /// Runs the function on the keys awaited by `notify_read`s, each along with the wait
+    /// of the oldest `notify_read` on it.
+    InspectObligations(ObligationsInspector<Key>),
+    /// A command tagged with the ID of the request it originates from, which is
+    /// processed within a `store_command` span carrying that ID.
+    Traced(String, Box<StoreCommand<Key, Value>>),
+    FlushAndClose(oneshot::Sender<StoreResult<()>>),
+}
+
+/// The value of a key read by `Store::get_and_watch`, along with the watch of its
+/// updates.
+pub type WatchedValue<Key, Value> = (Option<Value>, KeyWatch<Key, Value>);
+
+/// The function run by `StoreCommand::InspectObligations` on the awaited keys.
+pub type ObligationsInspector<Key> =
+    Box<dyn FnOnce(&mut dyn Iterator<Item = (&Key, Duration)>) + Send>;
+
+/// The number of commands queued for the background task of a `Store`, past which
+/// sending more waits (or fails, with `Store::write_nonblocking`).
+const COMMAND_CHANNEL_CAPACITY: usize = 100;
+
+/// The configuration of the background task serving a `Store`.
+#[derive(Clone, Debug)]
+pub struct StoreConfig {
+    /// When set, the `notify_read`s whose caller has gone away (e.g. dropped the future)
+    /// are swept at this interval. Otherwise, they are only cleaned up once their key is
+    /// written or deleted, and accumulate until then.
+    pub obligation_cleanup_interval: Option<Duration>,
+    /// When set, reads (including `notify_read`s) are sent through a channel of their
+    /// own, which the background task serves ahead of the writes, so that reads are not
+    /// stuck behind a backlog of large writes. Writes are still applied in the order they
+    /// are sent, but a read may then be served before a write sent ahead of it.
+    pub prioritize_reads: bool,
+    /// When set, bounds the number of acknowledged writes (e.g. `write_all`, but not the
+    /// fire-and-forget `write`) awaiting their reply: the writes beyond that bound are
+    /// rejected with `StoreError::Overloaded` instead of being queued, shedding load
+    /// rather than accumulating latency.
+    pub max_in_flight: Option<usize>,
+    /// When set, the background task flushes the memtables and syncs the write-ahead log
+    /// once the last clone of the store is dropped, before releasing the database, so
+    /// that the writes sent before the drop are durable even if the process exits right
+    /// after. `flush_and_close` remains the way to find out whether this succeeded.
+    pub sync_on_drop: bool,
+    /// When set, bounds the number of `notify_read`s pending on any single key: the
+    /// registrations beyond that bound are rejected with `StoreError::TooManyWaiters`, so
+    /// that a hot key can't accumulate waiters without bound. The `notify_read`s of the
+    /// key whose caller has gone away are cleaned up before counting its waiters.
+    pub max_waiters_per_key: Option<usize>,
+    /// When unset, `notify_read`, `notify_read_any` and `notify_read_traced` fail with
+    /// `StoreError::NotifyReadDisabled`, and the background task never keeps track of
+    /// pending reads: write-only stores don't pay for them. Set by default.
+    pub enable_notify_read: bool,
+    /// When set, a `write` of the value the key already holds is skipped, as with
+    /// `write_if_changed`: it neither reaches the database nor the change feed. Every
+    /// `write` then costs an extra read of the current value, which only pays off when
+    /// identical values are frequently rewritten.
+    pub dedup_writes: bool,
+    /// When set, the store maintains the version of its table returned by
+    /// `Store::version`, at the cost of an extra key written by each mutation. The table
+    /// must not be on the default column family, which holds the versions (see
+    /// `DBMap::open`).
+    pub track_version: bool,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            obligation_cleanup_interval: None,
+            prioritize_reads: false,
+            max_in_flight: None,
+            sync_on_drop: false,
+            max_waiters_per_key: None,
+            enable_notify_read: true,
+            dedup_writes: false,
+            track_version: false,
+        }
+    }
+}
+
+/// The options of `Store::scan`.
+#[derive(Clone, Debug)]
+pub struct ScanOpts<K> {
+    /// the smallest key to scan, from the first key of the store if `None`
+    pub start: Option<K>,
+    /// the key the scan stops before (excluded), up to the last key of the store if `None`
+    pub end: Option<K>,
+    /// the maximum number of entries to return, all of them if `None`
+    pub limit: Option<usize>,
+    /// scans in descending key order, from the end of the range
+    pub reverse: bool,
+    /// the number of bytes RocksDB prefetches when reading the SST files sequentially,
+    /// e.g. a few megabytes for large scans on high-latency disks, RocksDB's adaptive
+    /// readahead if `None`
+    pub readahead_size: Option<usize>,
+}
+
+// not derived, which would require the keys to be Default
+impl<K> Default for ScanOpts<K> {
+    fn default() -> Self {
+        ScanOpts {
+            start: None,
+            end: None,
+            limit: None,
+            reverse: false,
+            readahead_size: None,
+        }
+    }
 }
 
-#[derive(Clone)]
+/// A handle to a key-value store served by a background task, which processes the
+/// commands sent by all the clones of the handle one at a time, in the order they are
+/// received.
+///
+/// `write` and `remove` return as soon as their command is sent, before it is applied.
+/// The commands sent afterwards, from any clone, are still applied after them, so a read
+/// following a `write` observes it, unless reads are prioritized (see
+/// `StoreConfig::prioritize_reads`). In that case, the reads of the handle returned by
+/// `read_your_writes` are ordered after the writes sent before them, while the
+/// acknowledged writes (e.g. `write_all`) are applied once they return.
 pub struct Store<K, V> {
     channel: Sender<StoreCommand<K, V>>,
+    /// the channel of the reads, which the background task serves first
+    priority_channel: Sender<StoreCommand<K, V>>,
+    prioritize_reads: bool,
+    notify_read_enabled: bool,
+    /// the permits of the writes in flight, if they are bounded
+    in_flight: Option<Arc<Semaphore>>,
+    /// set once the background task has panicked
+    panicked: Arc<AtomicBool>,
+    /// closed once the background task is dropped, after `panicked` is set
+    terminated: Arc<Semaphore>,
+    changes: broadcast::Sender<Arc<RawChange>>,
+    metrics: Arc<StoreMetrics>,
+}
+
+// not derived, which would require the keys and values to be Clone
+impl<K, V> Clone for Store<K, V> {
+    fn clone(&self) -> Self {
+        Store {
+            channel: self.channel.clone(),
+            priority_channel: self.priority_channel.clone(),
+            prioritize_reads: self.prioritize_reads,
+            notify_read_enabled: self.notify_read_enabled,
+            in_flight: self.in_flight.clone(),
+            panicked: self.panicked.clone(),
+            terminated: self.terminated.clone(),
+            changes: self.changes.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
 }
 
 impl<Key, Value> Store<Key, Value>
@@ -51,53 +291,293 @@ where
     Value: Serialize + DeserializeOwned + Send + Clone + 'static,
 {
     pub fn new(keyed_db: rocks::DBMap<Key, Value>) -> Self {
-        let mut obligations = HashMap::<Key, VecDeque<oneshot::Sender<_>>>::new();
-        let (tx, mut rx) = channel(100);
-        tokio::spawn(async move {
-            while let Some(command) = rx.recv().await {
+        Self::new_with_config(keyed_db, StoreConfig::default())
+    }
+
+    pub fn new_with_config(keyed_db: rocks::DBMap<Key, Value>, config: StoreConfig) -> Self {
+        let (store, task) = Self::with_task(keyed_db, config);
+        tokio::spawn(task);
+        store
+    }
+
+    /// Creates a store whose background task runs on the runtime of the given handle,
+    /// rather than on the runtime of the caller. The store can then be used from any runtime.
+    pub fn new_on(keyed_db: rocks::DBMap<Key, Value>, handle: tokio::runtime::Handle) -> Self {
+        Self::new_on_with_config(keyed_db, handle, StoreConfig::default())
+    }
+
+    pub fn new_on_with_config(
+        keyed_db: rocks::DBMap<Key, Value>,
+        handle: tokio::runtime::Handle,
+        config: StoreConfig,
+    ) -> Self {
+        let (store, task) = Self::with_task(keyed_db, config);
+        handle.spawn(task);
+        store
+    }
+
+    /// Creates a store whose background task runs on a single-threaded runtime of its
+    /// own, on a dedicated thread, which isolates the storage work from the caller's
+    /// executor. No runtime needs to be active when calling this.
+    ///
+    /// The thread exits once the task terminates, i.e. once all the clones of the store
+    /// are dropped or the store is closed.
+    pub fn new_on_dedicated_thread(keyed_db: rocks::DBMap<Key, Value>) -> Self {
+        Self::new_on_dedicated_thread_with_config(keyed_db, StoreConfig::default())
+    }
+
+    pub fn new_on_dedicated_thread_with_config(
+        keyed_db: rocks::DBMap<Key, Value>,
+        config: StoreConfig,
+    ) -> Self {
+        let (store, task) = Self::with_task(keyed_db, config);
+        std::thread::Builder::new()
+            .name("typed-store".to_string())
+            .spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_time()
+                    .build()
+                    .expect("Failed to build the runtime of the store")
+                    .block_on(task)
+            })
+            .expect("Failed to spawn the thread of the store");
+        store
+    }
+
+    /// Creates a store along with the background task serving it, which is left for
+    /// the caller to spawn.
+    fn with_task(
+        keyed_db: rocks::DBMap<Key, Value>,
+        config: StoreConfig,
+    ) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        let mut obligations = Obligations::new();
+        let mut any_obligations = AnyObligations::default();
+        let mut loads = Loads::new();
+        let metrics = Arc::new(StoreMetrics::default());
+        let store_metrics = metrics.clone();
+        let (tx, mut rx) = channel(COMMAND_CHANNEL_CAPACITY);
+        let (priority_tx, mut priority_rx) = channel(COMMAND_CHANNEL_CAPACITY);
+        let prioritize_reads = config.prioritize_reads;
+        let notify_read_enabled = config.enable_notify_read;
+        let panicked = Arc::new(AtomicBool::new(false));
+        let terminated = Arc::new(Semaphore::new(0));
+        let (store_changes, _) = broadcast::channel(change_feed::CHANGE_FEED_CAPACITY);
+        let changes: Publisher<Key, Value> = Publisher::new(store_changes.clone());
+        let in_flight = config
+            .max_in_flight
+            .map(|max_in_flight| Arc::new(Semaphore::new(max_in_flight)));
+        let task = async move {
+            let mut close_reply = None;
+            let mut version = if config.track_version {
+                load_version(&keyed_db)
+            } else {
+                Err(StoreError::VersionNotTracked(
+                    "disabled by the configuration of the store".to_string(),
+                ))
+            };
+            let mut cleanup = config
+                .obligation_cleanup_interval
+                .map(tokio::time::interval);
+            loop {
+                let command = tokio::select! {
+                    biased;
+                    Some(command) = priority_rx.recv() => command,
+                    command = rx.recv() => match command {
+                        Some(command) => command,
+                        None => break,
+                    },
+                    _ = next_tick(&mut cleanup) => {
+                        remove_closed_obligations(&mut obligations, &metrics);
+                        any_obligations.remove_closed(&metrics);
+                        loads.retain(|_, results| results.strong_count() > 0);
+                        continue;
+                    }
+                };
+                let (command, span) = untrace(command);
+                let _entered = span.enter();
                 match command {
                     StoreCommand::Write(key, value) => {
-                        let _ = keyed_db.insert(&key, &value);
-                        if let Some(mut senders) = obligations.remove(&key) {
-                            while let Some(s) = senders.pop_front() {
-                                let _ = s.send(Ok(Some(value.clone())));
+                        if config.dedup_writes {
+                            if let Ok(false) = value_changed(&keyed_db, &key, &value) {
+                                continue;
                             }
                         }
+                        let response = mutate(&keyed_db, &mut version, |batch| {
+                            batch.insert_batch(&keyed_db, [(&key, &value)])
+                        });
+                        notify_obligations(&mut obligations, &key, Some(&value), &metrics);
+                        any_obligations.notify(&key, &value, &metrics);
+                        if response.is_ok() {
+                            changes.publish(|| Some(Change::Write(key, value)));
+                        }
                     }
                     StoreCommand::WriteAll(key_values, sender) => {
-                        let response =
-                            keyed_db.multi_insert(key_values.iter().map(|(k, v)| (k, v)));
+                        // the last value of each key wins
+                        let key_values = dedup_last_wins(key_values);
+                        let response = mutate(&keyed_db, &mut version, |batch| {
+                            batch.insert_batch(&keyed_db, key_values.iter().map(|(k, v)| (k, v)))
+                        });
 
+                        // notify the obligations only when the write was successful: the
+                        // commands are processed one at a time, so no obligation can be
+                        // registered between the insert and this fan-out
                         if response.is_ok() {
-                            for (key, _) in key_values {
-                                if let Some(mut senders) = obligations.remove(&key) {
-                                    while let Some(s) = senders.pop_front() {
-                                        let _ = s.send(Ok(None));
-                                    }
-                                }
+                            for (key, value) in &key_values {
+                                notify_obligations(&mut obligations, key, Some(value), &metrics);
+                                any_obligations.notify(key, value, &metrics);
                             }
+                            changes.publish(|| Some(write_batch(key_values)));
                         }
                         let _ = sender.send(response);
                     }
-                    StoreCommand::Delete(key) => {
-                        let _ = keyed_db.remove(&key);
-                        if let Some(mut senders) = obligations.remove(&key) {
-                            while let Some(s) = senders.pop_front() {
-                                let _ = s.send(Ok(None));
+                    StoreCommand::WriteAllIfAbsent(key_values, sender) => {
+                        let any_present: StoreResult<bool> =
+                            key_values.iter().try_fold(false, |found, (key, _)| {
+                                Ok(found || keyed_db.contains_key(key)?)
+                            });
+                        let response = any_present.and_then(|found| {
+                            if found {
+                                Ok(false)
+                            } else {
+                                mutate(&keyed_db, &mut version, |batch| {
+                                    let pairs = key_values.iter().map(|(k, v)| (k, v));
+                                    batch.insert_batch(&keyed_db, pairs)
+                                })
+                                .map(|()| true)
+                            }
+                        });
+
+                        if let Ok(true) = response {
+                            for (key, value) in &key_values {
+                                notify_obligations(&mut obligations, key, Some(value), &metrics);
+                                any_obligations.notify(key, value, &metrics);
+                            }
+                            changes.publish(|| Some(write_batch(key_values)));
+                        }
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::WriteIfChanged(key, value, sender) => {
+                        let changed = value_changed(&keyed_db, &key, &value);
+                        let response = changed.and_then(|changed| {
+                            if changed {
+                                mutate(&keyed_db, &mut version, |batch| {
+                                    batch.insert_batch(&keyed_db, [(&key, &value)])
+                                })?;
                             }
+                            Ok(changed)
+                        });
+
+                        if let Ok(true) = response {
+                            notify_obligations(&mut obligations, &key, Some(&value), &metrics);
+                            any_obligations.notify(&key, &value, &metrics);
+                            changes.publish(|| Some(Change::Write(key, value)));
+                        }
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::Append(value, next_key, sender) => {
+                        // the last key is read and the next one written by a single
+                        // command, so that concurrent appends are assigned distinct keys
+                        // a last key that fails to decode fails the append, rather than
+                        // restarting the keys from the first one over existing entries
+                        let last_key = match keyed_db.last_key() {
+                            Ok(last_key) => last_key,
+                            Err(e) => {
+                                let _ = sender.send(Err(e));
+                                continue;
+                            }
+                        };
+                        let key = next_key(last_key);
+                        let response = mutate(&keyed_db, &mut version, |batch| {
+                            batch.insert_batch(&keyed_db, [(&key, &value)])
+                        });
+                        let response = response.and_then(|()| {
+                            notify_obligations(&mut obligations, &key, Some(&value), &metrics);
+                            any_obligations.notify(&key, &value, &metrics);
+                            duplicate(&key)
+                        });
+                        if response.is_ok() {
+                            changes.publish(|| Some(Change::Write(key, value)));
+                        }
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::Delete(key) => {
+                        let response = mutate(&keyed_db, &mut version, |batch| {
+                            batch.delete_batch(&keyed_db, [&key])
+                        });
+                        notify_obligations(&mut obligations, &key, None, &metrics);
+                        if response.is_ok() {
+                            changes.publish(|| Some(Change::Delete(key)));
                         }
                     }
                     StoreCommand::DeleteAll(keys, sender) => {
-                        let response = keyed_db.multi_remove(keys.iter());
+                        let response = mutate(&keyed_db, &mut version, |batch| {
+                            batch.delete_batch(&keyed_db, keys.iter())
+                        });
                         // notify the obligations only when the delete was successful
                         if response.is_ok() {
-                            for key in keys {
-                                if let Some(mut senders) = obligations.remove(&key) {
-                                    while let Some(s) = senders.pop_front() {
-                                        let _ = s.send(Ok(None));
-                                    }
-                                }
+                            for key in &keys {
+                                notify_obligations(&mut obligations, key, None, &metrics);
+                            }
+                            changes.publish(|| Some(delete_batch(keys)));
+                        }
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::DeletePrefix(prefix, sender) => {
+                        let mut deleted = 0;
+                        let response = mutate(&keyed_db, &mut version, |batch| {
+                            let (batch, count) = keyed_db.delete_prefix_batch(batch, &prefix)?;
+                            deleted = count;
+                            Ok(batch)
+                        })
+                        .map(|()| deleted);
+                        if response.is_ok() {
+                            notify_prefix_obligations(&mut obligations, &prefix, &metrics);
+                            changes.publish(|| Some(Change::DeletePrefix(prefix)));
+                        }
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::DeleteAllExisting(keys, sender) => {
+                        let response = keyed_db.multi_contains_key(keys.iter()).and_then(|found| {
+                            mutate(&keyed_db, &mut version, |batch| {
+                                batch.delete_batch(&keyed_db, keys.iter())
+                            })?;
+                            Ok(found)
+                        });
+                        let response = response.map(|found| {
+                            for key in &keys {
+                                notify_obligations(&mut obligations, key, None, &metrics);
+                            }
+                            // the keys are handed out to the caller as well
+                            changes.publish(|| {
+                                let keys = keys.iter().map(duplicate).collect::<StoreResult<_>>();
+                                keys.ok().map(delete_batch)
+                            });
+                            keys.into_iter()
+                                .zip(found)
+                                .filter_map(|(key, found)| found.then(|| key))
+                                .collect()
+                        });
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::CompareAndDelete(key, expected, sender) => {
+                        let matches = bincode::serialize(&expected)
+                            .map_err(StoreError::from)
+                            .and_then(|expected_buf| {
+                                let current = keyed_db.get_raw_pinned(&key)?;
+                                Ok(current.as_deref() == Some(&expected_buf[..]))
+                            });
+                        let response = matches.and_then(|matches| {
+                            if matches {
+                                mutate(&keyed_db, &mut version, |batch| {
+                                    batch.delete_batch(&keyed_db, [&key])
+                                })?;
                             }
+                            Ok(matches)
+                        });
+
+                        if let Ok(true) = response {
+                            notify_obligations(&mut obligations, &key, None, &metrics);
+                            changes.publish(|| Some(Change::Delete(key)));
                         }
                         let _ = sender.send(response);
                     }
@@ -106,24 +586,609 @@ where
                         let _ = sender.send(response);
                     }
                     StoreCommand::ReadAll(keys, sender) => {
-                        let response = keyed_db.multi_get(keys.as_slice());
+                        let response = dedup_multi_get(&keys, |keys| keyed_db.multi_get(keys));
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::ContainsAll(keys, sender) => {
+                        let response = keyed_db.multi_contains_key(keys.iter());
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::ReadWithDeadline(key, deadline, sender) => {
+                        // the caller has given up on this read already, skip the lookup
+                        let response = if Instant::now() >= deadline {
+                            Err(StoreError::Deadline)
+                        } else {
+                            keyed_db.get(&key)
+                        };
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::ReadAllWithDeadline(keys, deadline, sender) => {
+                        let response = if Instant::now() >= deadline {
+                            Err(StoreError::Deadline)
+                        } else {
+                            dedup_multi_get(&keys, |keys| keyed_db.multi_get(keys))
+                        };
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::ScanPrefixPage(prefix, after, limit, sender) => {
+                        let response = keyed_db.prefix_page_raw(&prefix, after, limit);
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::ReadRangeOrdered(start, end, sender) => {
+                        let response = keyed_db
+                            .range_iter(&start, &end)
+                            .map(|entries| entries.collect());
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::Scan(opts, sender) => {
+                        let response = keyed_db.scan_raw(
+                            opts.start,
+                            opts.end,
+                            opts.limit,
+                            opts.reverse,
+                            opts.readahead_size,
+                        );
                         let _ = sender.send(response);
                     }
                     StoreCommand::NotifyRead(key, sender) => {
+                        // checked here as well, for the commands wrapped by other methods
+                        if !config.enable_notify_read {
+                            let _ = sender.send(Err(StoreError::NotifyReadDisabled));
+                            continue;
+                        }
                         let response = keyed_db.get(&key);
                         if let Ok(Some(_)) = response {
+                            metrics.record_notify_read_immediate();
                             let _ = sender.send(response);
                         } else {
-                            obligations
-                                .entry(key)
-                                .or_insert_with(VecDeque::new)
-                                .push_back(sender)
+                            let too_many = config.max_waiters_per_key.map_or(false, |max| {
+                                let waiters = obligations.get_mut(&key).map_or(0, |senders| {
+                                    remove_closed_senders(senders, &metrics);
+                                    senders.len()
+                                });
+                                waiters >= max
+                            });
+                            if too_many {
+                                let _ = sender.send(Err(StoreError::TooManyWaiters));
+                            } else {
+                                metrics.record_notify_read_registered();
+                                obligations
+                                    .entry(key)
+                                    .or_insert_with(VecDeque::new)
+                                    .push_back((Instant::now(), sender))
+                            }
+                        }
+                    }
+                    StoreCommand::GetAndWatch(key, key_buf, sender) => {
+                        // no change can be applied between the subscription and the read
+                        let watch = KeyWatch::new(key_buf, changes.subscribe());
+                        let response = keyed_db.get(&key).map(|value| (value, watch));
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::Version(sender) => {
+                        let _ = sender.send(version.clone());
+                    }
+                    StoreCommand::ReadOrClaim(key, sender) => {
+                        let response = keyed_db.get(&key).and_then(|value| {
+                            if let Some(value) = value {
+                                return Ok(Claim::Present(value));
+                            }
+                            let key_buf = rocks::be_fix_int_ser(&key)?;
+                            // the loader of the key may have gone away without completing
+                            if let Some(results) = loads.get(&key_buf).and_then(Weak::upgrade) {
+                                return Ok(Claim::Wait(key, results.subscribe()));
+                            }
+                            let results = Arc::new(broadcast::channel(1).0);
+                            loads.insert(key_buf, Arc::downgrade(&results));
+                            Ok(Claim::Load(key, results))
+                        });
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::Populate(key, loaded, sender) => {
+                        if let Ok(key_buf) = rocks::be_fix_int_ser(&key) {
+                            loads.remove(&key_buf);
                         }
+                        let response = loaded.and_then(|value| {
+                            mutate(&keyed_db, &mut version, |batch| {
+                                batch.insert_batch(&keyed_db, [(&key, &value)])
+                            })?;
+                            Ok(value)
+                        });
+                        if let Ok(value) = &response {
+                            notify_obligations(&mut obligations, &key, Some(value), &metrics);
+                            any_obligations.notify(&key, value, &metrics);
+                            changes.publish(|| Some(Change::Write(key, value.clone())));
+                        }
+                        let _ = sender.send(response);
+                    }
+                    StoreCommand::NotifyReadAny(mut keys, sender) => {
+                        if !config.enable_notify_read {
+                            let _ = sender.send(Err(StoreError::NotifyReadDisabled));
+                            continue;
+                        }
+                        // the first of the keys holding a value, if any
+                        let present = keys
+                            .iter()
+                            .enumerate()
+                            .find_map(|(position, key)| {
+                                let value = keyed_db.get(key);
+                                value.map(|v| v.map(|v| (position, v))).transpose()
+                            })
+                            .transpose();
+                        match present {
+                            Ok(Some((position, value))) => {
+                                metrics.record_notify_read_immediate();
+                                let _ = sender.send(Ok((keys.swap_remove(position), value)));
+                            }
+                            Ok(None) => match keys
+                                .into_iter()
+                                .map(|key| Ok((rocks::be_fix_int_ser(&key)?, key)))
+                                .collect::<StoreResult<Vec<_>>>()
+                            {
+                                Ok(keys) => {
+                                    metrics.record_notify_read_registered();
+                                    any_obligations.register(keys, sender);
+                                }
+                                Err(e) => {
+                                    let _ = sender.send(Err(e));
+                                }
+                            },
+                            Err(e) => {
+                                let _ = sender.send(Err(e));
+                            }
+                        }
+                    }
+                    StoreCommand::InspectObligations(inspect) => {
+                        let mut awaited = obligations.iter().filter_map(|(key, senders)| {
+                            let (registered, _) = senders.front()?;
+                            Some((key, registered.elapsed()))
+                        });
+                        inspect(&mut awaited);
+                    }
+                    StoreCommand::Ping(sender) => {
+                        let _ = sender.send(keyed_db.ping());
                     }
+                    StoreCommand::Traced(..) => {
+                        unreachable!("Traced commands are unwrapped before being processed")
+                    }
+                    StoreCommand::FlushAndClose(sender) => {
+                        let response = keyed_db.flush();
+                        // nothing will be written for the pending obligations anymore
+                        for (_, mut senders) in obligations.drain() {
+                            metrics.record_notify_reads_released(senders.len());
+                            while let Some((_, s)) = senders.pop_front() {
+                                let _ = s.send(Err(StoreError::StoreClosed));
+                            }
+                        }
+                        for any_sender in any_obligations.drain(&metrics) {
+                            let _ = any_sender.send(Err(StoreError::StoreClosed));
+                        }
+                        close_reply = Some((sender, response));
+                        break;
+                    }
+                }
+            }
+            if close_reply.is_none() && config.sync_on_drop {
+                // all the clones are gone, nobody is left to report a failure to
+                if let Err(e) = keyed_db.flush().and_then(|()| keyed_db.flush_wal(true)) {
+                    tracing::warn!("Failed to sync the store on drop: {e}");
                 }
             }
-        });
-        Self { channel: tx }
+            // release the database before acknowledging the close, so that
+            // it can be reopened as soon as the caller is notified
+            drop(keyed_db);
+            if let Some((sender, response)) = close_reply {
+                let _ = sender.send(response);
+            }
+        };
+        let store = Self {
+            channel: tx,
+            priority_channel: priority_tx,
+            prioritize_reads,
+            notify_read_enabled,
+            in_flight,
+            panicked: panicked.clone(),
+            terminated: terminated.clone(),
+            changes: store_changes,
+            metrics: store_metrics,
+        };
+        let task = CatchPanic {
+            task: Box::pin(task),
+            panicked,
+            terminated,
+        };
+        (store, task)
+    }
+}
+
+/// Whether a key of a store holding optional values is set, and to what.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Presence<T> {
+    /// the key holds `Some(value)`
+    Present(T),
+    /// the key holds `None`
+    StoredNone,
+    /// the key holds nothing
+    Absent,
+}
+
+impl<Key, T> Store<Key, Option<T>>
+where
+    Key: Serialize + DeserializeOwned + Send,
+    T: Serialize + DeserializeOwned + Send,
+{
+    /// Reads the value for the provided key, telling apart a stored `None` from an absent
+    /// key, which `read` reports alike.
+    pub async fn get_defined(&self, key: Key) -> StoreResult<Presence<T>> {
+        let presence = match self.read(key).await? {
+            Some(Some(value)) => Presence::Present(value),
+            Some(None) => Presence::StoredNone,
+            None => Presence::Absent,
+        };
+        Ok(presence)
+    }
+}
+
+impl<Key, Value> Store<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send + Clone + 'static,
+    Value: Serialize + DeserializeOwned + Send,
+{
+    /// Reports the keys awaited by `notify_read`s, each along with how long the oldest
+    /// `notify_read` on it has been waiting, e.g. to find out what a stuck pipeline is
+    /// blocked on. This includes the `notify_read`s whose caller has gone away, but which
+    /// have not been cleaned up yet.
+    pub async fn debug_obligations(&self) -> StoreResult<Vec<(Key, Duration)>> {
+        let (sender, receiver) = oneshot::channel();
+        let inspect = move |awaited: &mut dyn Iterator<Item = (&Key, Duration)>| {
+            let awaited = awaited.map(|(key, wait)| (key.clone(), wait)).collect();
+            let _ = sender.send(Ok(awaited));
+        };
+        let command = StoreCommand::InspectObligations(Box::new(inspect));
+        self.request(&self.channel, command, receiver).await
+    }
+}
+
+/// The pending `notify_read`s, along with the time they were registered at.
+type Obligations<Key, Value> =
+    HashMap<Key, VecDeque<(Instant, oneshot::Sender<StoreResult<Option<Value>>>)>>;
+
+/// Resolves the `notify_read`s pending on the key with the given value, recording
+/// how long each of them waited.
+fn notify_obligations<Key: Hash + Eq, Value: Clone>(
+    obligations: &mut Obligations<Key, Value>,
+    key: &Key,
+    value: Option<&Value>,
+    metrics: &StoreMetrics,
+) {
+    if obligations.is_empty() {
+        return;
+    }
+    if let Some(mut senders) = obligations.remove(key) {
+        metrics.record_notify_reads_released(senders.len());
+        while let Some((registered, s)) = senders.pop_front() {
+            metrics.record_notify_read_wait(registered.elapsed());
+            let _ = s.send(Ok(value.cloned()));
+        }
+    }
+}
+
+/// Returns true if the key doesn't hold the value. The values are compared in their
+/// serialized form, which spares requiring them to be PartialEq.
+fn value_changed<Key: Serialize + DeserializeOwned, Value: Serialize + DeserializeOwned>(
+    keyed_db: &rocks::DBMap<Key, Value>,
+    key: &Key,
+    value: &Value,
+) -> StoreResult<bool> {
+    let value_buf = bincode::serialize(value)?;
+    let current = keyed_db.get_raw_pinned(key)?;
+    Ok(current.as_deref() != Some(&value_buf[..]))
+}
+
+/// Resolves with `None` the obligations whose serialized key starts with `prefix`.
+fn notify_prefix_obligations<Key: Serialize, Value>(
+    obligations: &mut Obligations<Key, Value>,
+    prefix: &[u8],
+    metrics: &StoreMetrics,
+) {
+    obligations.retain(|key, senders| {
+        let in_prefix = rocks::be_fix_int_ser(key).map_or(false, |key| key.starts_with(prefix));
+        if in_prefix {
+            metrics.record_notify_reads_released(senders.len());
+            while let Some((registered, s)) = senders.pop_front() {
+                metrics.record_notify_read_wait(registered.elapsed());
+                let _ = s.send(Ok(None));
+            }
+        }
+        !in_prefix
+    });
+}
+
+/// The outcome of a `ReadOrClaim` command, which `Store::get_or_populate` acts upon.
+pub enum Claim<Key, Value> {
+    /// the key holds this value
+    Present(Value),
+    /// another caller is loading the key, and will send the loaded value
+    Wait(Key, broadcast::Receiver<StoreResult<Value>>),
+    /// the caller is to load the key, and send the loaded value to the waiters
+    Load(Key, Arc<broadcast::Sender<StoreResult<Value>>>),
+}
+
+/// Reads the version of the table on startup, increased right away so that the versions
+/// handed out before the restart are never handed out again.
+fn load_version<K, V>(keyed_db: &rocks::DBMap<K, V>) -> StoreResult<u64> {
+    let version = keyed_db.stored_version()? + 1;
+    keyed_db.store_version(version)?;
+    Ok(version)
+}
+
+/// Writes the batch of a mutation of the table, built by `mutation`. When the version of
+/// the table is tracked, the next version is written by the same batch, so that it is
+/// only increased along with the mutation.
+fn mutate<K, V>(
+    keyed_db: &rocks::DBMap<K, V>,
+    version: &mut StoreResult<u64>,
+    mutation: impl FnOnce(rocks::DBBatch) -> StoreResult<rocks::DBBatch>,
+) -> StoreResult<()> {
+    let batch = mutation(keyed_db.batch())?;
+    match version {
+        Ok(current) => {
+            let next = *current + 1;
+            batch.put_version(keyed_db, next)?.write()?;
+            *current = next;
+            Ok(())
+        }
+        Err(_) => batch.write(),
+    }
+}
+
+/// The keys being loaded by `get_or_populate`, by their serialized form, along with the
+/// channel of the loaded value. Only the loading caller holds the channel, so that the
+/// waiters find out if it goes away without completing.
+type Loads<Value> = HashMap<Vec<u8>, Weak<broadcast::Sender<StoreResult<Value>>>>;
+
+/// A `notify_read_any` pending: the keys it waits on, along with their serialized form.
+struct AnyRead<Key, Value> {
+    keys: Vec<(Vec<u8>, Key)>,
+    registered: Instant,
+    sender: oneshot::Sender<StoreResult<(Key, Value)>>,
+}
+
+/// The `notify_read_any`s pending, each waiting on several keys. They are indexed by the
+/// serialized form of the keys, which spares requiring the keys to be Clone.
+struct AnyObligations<Key, Value> {
+    next_id: u64,
+    by_key: HashMap<Vec<u8>, Vec<u64>>,
+    reads: HashMap<u64, AnyRead<Key, Value>>,
+}
+
+impl<Key, Value> Default for AnyObligations<Key, Value> {
+    fn default() -> Self {
+        AnyObligations {
+            next_id: 0,
+            by_key: HashMap::new(),
+            reads: HashMap::new(),
+        }
+    }
+}
+
+impl<Key: Serialize, Value: Clone> AnyObligations<Key, Value> {
+    fn register(
+        &mut self,
+        keys: Vec<(Vec<u8>, Key)>,
+        sender: oneshot::Sender<StoreResult<(Key, Value)>>,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+        for (key_buf, _) in &keys {
+            self.by_key.entry(key_buf.clone()).or_default().push(id);
+        }
+        let registered = Instant::now();
+        self.reads.insert(
+            id,
+            AnyRead {
+                keys,
+                registered,
+                sender,
+            },
+        );
+    }
+
+    /// Resolves the `notify_read_any`s waiting on the key with the given value, and
+    /// drops them from the other keys they wait on.
+    fn notify(&mut self, key: &Key, value: &Value, metrics: &StoreMetrics) {
+        if self.reads.is_empty() {
+            return;
+        }
+        let key_buf = match rocks::be_fix_int_ser(key) {
+            Ok(key_buf) => key_buf,
+            // no read could register on a key that does not serialize
+            Err(_) => return,
+        };
+        for id in self.by_key.remove(&key_buf).unwrap_or_default() {
+            if let Some(mut read) = self.remove(id) {
+                metrics.record_notify_reads_released(1);
+                metrics.record_notify_read_wait(read.registered.elapsed());
+                let position = read.keys.iter().position(|(k, _)| *k == key_buf);
+                let (_, key) = read.keys.swap_remove(position.unwrap_or_default());
+                let _ = read.sender.send(Ok((key, value.clone())));
+            }
+        }
+    }
+
+    /// Drops the read from the index of each of its keys.
+    fn remove(&mut self, id: u64) -> Option<AnyRead<Key, Value>> {
+        let read = self.reads.remove(&id)?;
+        for (key_buf, _) in &read.keys {
+            if let Some(ids) = self.by_key.get_mut(key_buf) {
+                ids.retain(|i| *i != id);
+                if ids.is_empty() {
+                    self.by_key.remove(key_buf);
+                }
+            }
+        }
+        Some(read)
+    }
+
+    /// Drops the `notify_read_any`s whose caller has gone away.
+    fn remove_closed(&mut self, metrics: &StoreMetrics) {
+        let closed: Vec<_> = self
+            .reads
+            .iter()
+            .filter(|(_, read)| read.sender.is_closed())
+            .map(|(id, _)| *id)
+            .collect();
+        metrics.record_notify_reads_released(closed.len());
+        for id in closed {
+            self.remove(id);
+        }
+    }
+
+    /// Removes all the `notify_read_any`s, returning their senders.
+    fn drain(&mut self, metrics: &StoreMetrics) -> Vec<oneshot::Sender<StoreResult<(Key, Value)>>> {
+        metrics.record_notify_reads_released(self.reads.len());
+        self.by_key.clear();
+        self.reads.drain().map(|(_, read)| read.sender).collect()
+    }
+}
+
+/// The change of a command writing several entries.
+fn write_batch<Key, Value>(key_values: Vec<(Key, Value)>) -> Change<Key, Value> {
+    let writes = key_values.into_iter();
+    Change::Batch(
+        writes
+            .map(|(key, value)| Change::Write(key, value))
+            .collect(),
+    )
+}
+
+/// The change of a command deleting several entries.
+fn delete_batch<Key, Value>(keys: Vec<Key>) -> Change<Key, Value> {
+    Change::Batch(keys.into_iter().map(Change::Delete).collect())
+}
+
+/// Copies a value through its serialization, which spares requiring it to be Clone.
+fn duplicate<T: Serialize + DeserializeOwned>(value: &T) -> StoreResult<T> {
+    Ok(bincode::deserialize(&bincode::serialize(value)?)?)
+}
+
+/// Unwraps a traced command, returning it along with the span to process it in.
+fn untrace<Key, Value>(
+    mut command: StoreCommand<Key, Value>,
+) -> (StoreCommand<Key, Value>, tracing::Span) {
+    let mut span = tracing::Span::none();
+    while let StoreCommand::Traced(request_id, traced) = command {
+        span = tracing::debug_span!("store_command", request_id = %request_id);
+        command = *traced;
+    }
+    (command, span)
+}
+
+/// Keeps the last of the pairs sharing a key, at its position among the others.
+fn dedup_last_wins<Key: Hash + Eq, Value>(pairs: Vec<(Key, Value)>) -> Vec<(Key, Value)> {
+    let keep: Vec<bool> = {
+        let mut last = HashMap::with_capacity(pairs.len());
+        for (i, (key, _)) in pairs.iter().enumerate() {
+            last.insert(key, i);
+        }
+        if last.len() == pairs.len() {
+            return pairs;
+        }
+        (0..pairs.len())
+            .map(|i| last.get(&pairs[i].0) == Some(&i))
+            .collect()
+    };
+    pairs
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(pair, keep)| keep.then(|| pair))
+        .collect()
+}
+
+/// Looks up each distinct key of `keys` once through `multi_get`, then maps the values
+/// back to the positions of `keys`.
+fn dedup_multi_get<'k, Key: Hash + Eq, Value: Clone>(
+    keys: &'k [Key],
+    multi_get: impl FnOnce(Vec<&'k Key>) -> StoreResult<Vec<Option<Value>>>,
+) -> StoreResult<Vec<Option<Value>>> {
+    let mut index = HashMap::with_capacity(keys.len());
+    let mut distinct = Vec::with_capacity(keys.len());
+    let mut positions = Vec::with_capacity(keys.len());
+    for key in keys {
+        positions.push(*index.entry(key).or_insert_with(|| {
+            distinct.push(key);
+            distinct.len() - 1
+        }));
+    }
+
+    if distinct.len() == keys.len() {
+        return multi_get(distinct);
+    }
+    let values = multi_get(distinct)?;
+    Ok(positions.into_iter().map(|i| values[i].clone()).collect())
+}
+
+/// Drops the `notify_read`s whose caller has gone away.
+fn remove_closed_obligations<Key: Hash + Eq, Value>(
+    obligations: &mut Obligations<Key, Value>,
+    metrics: &StoreMetrics,
+) {
+    obligations.retain(|_, senders| {
+        remove_closed_senders(senders, metrics);
+        !senders.is_empty()
+    });
+}
+
+/// Removes the `notify_read`s of a key whose caller has gone away.
+fn remove_closed_senders<T>(
+    senders: &mut VecDeque<(Instant, oneshot::Sender<T>)>,
+    metrics: &StoreMetrics,
+) {
+    let pending = senders.len();
+    senders.retain(|(_, s)| !s.is_closed());
+    metrics.record_notify_reads_released(pending - senders.len());
+}
+
+/// Completes on the next tick of the interval, or never if there is none.
+async fn next_tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Runs the background task of a store, recording whether it panicked. The panic is
+/// contained, and the task terminates: the store then reports
+/// `StoreError::BackgroundTaskPanicked` to the callers.
+///
+/// The channels of the task are dropped as it unwinds, before the panic is recorded:
+/// `terminated` tells the callers when the outcome is settled.
+struct CatchPanic<F> {
+    task: Pin<Box<F>>,
+    panicked: Arc<AtomicBool>,
+    terminated: Arc<Semaphore>,
+}
+
+impl<F> Drop for CatchPanic<F> {
+    fn drop(&mut self) {
+        self.terminated.close();
+    }
+}
+
+impl<F: Future<Output = ()>> Future for CatchPanic<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let task = &mut self.task;
+        match std::panic::catch_unwind(AssertUnwindSafe(|| task.as_mut().poll(cx))) {
+            Ok(poll) => poll,
+            Err(_) => {
+                self.panicked.store(true, Ordering::SeqCst);
+                Poll::Ready(())
+            }
+        }
     }
 }
 
@@ -132,38 +1197,251 @@ where
     Key: Serialize + DeserializeOwned + Send,
     Value: Serialize + DeserializeOwned + Send,
 {
+    /// Returns true if the background task of the store panicked. The store can't serve
+    /// any command afterwards, and must be recreated.
+    pub fn has_panicked(&self) -> bool {
+        self.panicked.load(Ordering::SeqCst)
+    }
+
+    /// The error of a command the background task did not serve, as it has terminated:
+    /// `StoreError::BackgroundTaskPanicked` if it panicked, `StoreError::StoreClosed`
+    /// otherwise.
+    async fn task_error(&self) -> StoreError {
+        // nothing ever adds a permit: this waits for the task to be dropped
+        let _ = self.terminated.acquire().await;
+        self.terminated_error()
+    }
+
+    fn terminated_error(&self) -> StoreError {
+        if self.has_panicked() {
+            StoreError::BackgroundTaskPanicked
+        } else {
+            StoreError::StoreClosed
+        }
+    }
+
+    /// Sends the command through `channel`, and waits for its reply on `receiver`. A
+    /// command the background task does not serve, as it has terminated, fails with
+    /// `task_error`.
+    async fn request<T>(
+        &self,
+        channel: &Sender<StoreCommand<Key, Value>>,
+        command: StoreCommand<Key, Value>,
+        receiver: oneshot::Receiver<StoreResult<T>>,
+    ) -> StoreResult<T> {
+        if channel.send(command).await.is_err() {
+            return Err(self.task_error().await);
+        }
+        match receiver.await {
+            Ok(reply) => reply,
+            Err(_) => Err(self.task_error().await),
+        }
+    }
+
+    /// Returns a handle to this store whose reads are never prioritized, and are therefore
+    /// ordered after all the writes sent before them (by any clone), including those
+    /// sent with `write` that have not been applied yet.
+    pub fn read_your_writes(&self) -> Self {
+        let mut store = self.clone();
+        store.prioritize_reads = false;
+        store
+    }
+
+    /// The channel to send reads through.
+    fn read_channel(&self) -> &Sender<StoreCommand<Key, Value>> {
+        if self.prioritize_reads {
+            &self.priority_channel
+        } else {
+            &self.channel
+        }
+    }
+
+    /// Reserves a slot for a write in flight, held until the write is acknowledged.
+    fn acquire_in_flight(&self) -> StoreResult<Option<OwnedSemaphorePermit>> {
+        match &self.in_flight {
+            Some(in_flight) => in_flight
+                .clone()
+                .try_acquire_owned()
+                .map(Some)
+                .map_err(|_| StoreError::Overloaded),
+            None => Ok(None),
+        }
+    }
+
+    /// Subscribes to the feed of all the mutations applied by this store from now on.
+    pub fn change_feed(&self) -> ChangeFeed<Key, Value> {
+        ChangeFeed::new(self.changes.subscribe())
+    }
+
+    /// Returns a handle to this store that can only read from it.
+    pub fn reader(&self) -> StoreReader<Key, Value> {
+        StoreReader::new(self.clone())
+    }
+
+    /// The metrics collected by this store, shared by all its clones.
+    pub fn metrics(&self) -> Arc<StoreMetrics> {
+        self.metrics.clone()
+    }
+
     pub async fn write(&self, key: Key, value: Value) {
-        if let Err(e) = self.channel.send(StoreCommand::Write(key, value)).await {
-            panic!("Failed to send Write command to store: {e}");
+        if self
+            .channel
+            .send(StoreCommand::Write(key, value))
+            .await
+            .is_err()
+        {
+            assert!(
+                !matches!(self.task_error().await, StoreError::BackgroundTaskPanicked),
+                "The background task of the store panicked"
+            );
+            // the command is unacknowledged: once the store is closed, it is dropped
+            tracing::warn!("Dropped a Write command sent to a closed store");
+        }
+    }
+
+    /// Same as `write`, but fails right away with `StoreError::WouldBlock` rather than
+    /// waiting when the queue of the background task is full, e.g. to shed load when the
+    /// store is saturated, or to write from outside an async context. It also does while
+    /// the background task is being torn down, after which it fails with the error the
+    /// other commands get.
+    pub fn write_nonblocking(&self, key: Key, value: Value) -> StoreResult<()> {
+        match self.channel.try_send(StoreCommand::Write(key, value)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(StoreError::WouldBlock),
+            Err(TrySendError::Closed(_)) => match self.terminated.try_acquire() {
+                Err(TryAcquireError::Closed) => Err(self.terminated_error()),
+                // the task is still being dropped, and a retry gets the outcome
+                _ => Err(StoreError::WouldBlock),
+            },
         }
     }
 
     /// Atomically writes all the key-value pairs in storage.
     /// If the operation is successful, then the result will be a non
     /// error empty result. Otherwise the error is returned.
+    ///
+    /// When a key appears several times, the last of its values wins: it is the one
+    /// stored, and the only one passed to the `notify_read`s of the key.
     pub async fn write_all(
         &self,
         key_value_pairs: impl IntoIterator<Item = (Key, Value)>,
     ) -> StoreResult<()> {
+        let key_values: Vec<_> = key_value_pairs.into_iter().collect();
+        if key_values.is_empty() {
+            return Ok(());
+        }
+        let _permit = self.acquire_in_flight()?;
         let (sender, receiver) = oneshot::channel();
-        if let Err(e) = self
-            .channel
-            .send(StoreCommand::WriteAll(
-                key_value_pairs.into_iter().collect(),
-                sender,
-            ))
-            .await
-        {
-            panic!("Failed to send WriteAll command to store: {e}");
+        let command = StoreCommand::WriteAll(key_values, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Atomically writes all the key-value pairs, provided that none of the keys is
+    /// already present in storage. Returns `true` if the pairs were written, and
+    /// `false` (having written nothing) if any of the keys already existed.
+    ///
+    /// The check and the write are performed as a single command of the store, so they
+    /// can't be interleaved with other commands sent to it.
+    pub async fn write_all_if_absent(
+        &self,
+        key_value_pairs: impl IntoIterator<Item = (Key, Value)>,
+    ) -> StoreResult<bool> {
+        let key_values: Vec<_> = key_value_pairs.into_iter().collect();
+        // none of no keys is present
+        if key_values.is_empty() {
+            return Ok(true);
+        }
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::WriteAllIfAbsent(key_values, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Writes the value for the key, unless it is already the value stored for it.
+    /// Returns whether the value was written, i.e. changed.
+    ///
+    /// When the value is unchanged, nothing is written to the database and no
+    /// `notify_read` is released, which spares re-applying the same state.
+    pub async fn write_if_changed(&self, key: Key, value: Value) -> StoreResult<bool> {
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::WriteIfChanged(key, value, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Writes the value under the key `next_key` derives from the last key of the store
+    /// (`None` if it is empty), and returns that key. See `AppendOnlyLog`.
+    pub(crate) async fn append(
+        &self,
+        value: Value,
+        next_key: fn(Option<Key>) -> Key,
+    ) -> StoreResult<Key> {
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::Append(value, next_key, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Like `write_all`, but gives up with `StoreError::Timeout` if the write is not
+    /// acknowledged within the timeout, e.g. as the background task is stuck. The write
+    /// may then still be applied later, and counts against `StoreConfig::max_in_flight`
+    /// until it is.
+    pub async fn write_all_timeout(
+        &self,
+        key_value_pairs: impl IntoIterator<Item = (Key, Value)>,
+        timeout: Duration,
+    ) -> StoreResult<()> {
+        let key_values: Vec<_> = key_value_pairs.into_iter().collect();
+        if key_values.is_empty() {
+            return Ok(());
         }
-        receiver
+        let permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::WriteAll(key_values, sender);
+        self.acknowledge_within(command, receiver, permit, timeout)
             .await
-            .expect("Failed to receive reply to WriteAll command from store")
+    }
+
+    /// Sends an acknowledged command, and awaits its reply within the timeout. Once the
+    /// command is queued, it may still be applied past the timeout: its in-flight permit
+    /// is then held until it is, so that the writes in flight are all counted.
+    async fn acknowledge_within<T: Send + 'static>(
+        &self,
+        command: StoreCommand<Key, Value>,
+        mut receiver: oneshot::Receiver<StoreResult<T>>,
+        permit: Option<OwnedSemaphorePermit>,
+        timeout: Duration,
+    ) -> StoreResult<T> {
+        let deadline = Instant::now() + timeout;
+        match tokio::time::timeout_at(deadline, self.channel.send(command)).await {
+            // the command was never queued
+            Err(_elapsed) => return Err(StoreError::Timeout),
+            Ok(Err(_)) => return Err(self.task_error().await),
+            Ok(Ok(())) => (),
+        }
+        match tokio::time::timeout_at(deadline, &mut receiver).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => Err(self.task_error().await),
+            Err(_elapsed) => {
+                if let Some(permit) = permit {
+                    tokio::spawn(async move {
+                        let _ = receiver.await;
+                        drop(permit);
+                    });
+                }
+                Err(StoreError::Timeout)
+            }
+        }
     }
 
     pub async fn remove(&self, key: Key) {
-        if let Err(e) = self.channel.send(StoreCommand::Delete(key)).await {
-            panic!("Failed to send Delete command to store: {e}");
+        if self.channel.send(StoreCommand::Delete(key)).await.is_err() {
+            assert!(
+                !matches!(self.task_error().await, StoreError::BackgroundTaskPanicked),
+                "The background task of the store panicked"
+            );
+            // the command is unacknowledged: once the store is closed, it is dropped
+            tracing::warn!("Dropped a Delete command sent to a closed store");
         }
     }
 
@@ -171,27 +1449,79 @@ where
     /// If the operation is successful, then the result will be a non
     /// error empty result. Otherwise the error is returned.
     pub async fn remove_all(&self, keys: impl IntoIterator<Item = Key>) -> StoreResult<()> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let _permit = self.acquire_in_flight()?;
         let (sender, receiver) = oneshot::channel();
-        if let Err(e) = self
-            .channel
-            .send(StoreCommand::DeleteAll(keys.into_iter().collect(), sender))
-            .await
-        {
-            panic!("Failed to send DeleteAll command to store: {e}");
+        let command = StoreCommand::DeleteAll(keys, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Atomically removes all the keys whose serialized form starts with the serialized
+    /// `prefix` (e.g. all the keys `(tenant, _)` of a tenant), and returns how many were
+    /// removed. The `notify_read`s pending on the removed keys resolve with `None`.
+    pub async fn delete_prefix<P: Serialize>(&self, prefix: P) -> StoreResult<u64> {
+        let prefix = rocks::be_fix_int_ser(&prefix)?;
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::DeletePrefix(prefix, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Like `remove_all`, but gives up with `StoreError::Timeout` if the removal is not
+    /// acknowledged within the timeout. The removal may then still be applied later, and
+    /// counts against `StoreConfig::max_in_flight` until it is.
+    pub async fn remove_all_timeout(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+        timeout: Duration,
+    ) -> StoreResult<()> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return Ok(());
         }
-        receiver
+        let permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::DeleteAll(keys, sender);
+        self.acknowledge_within(command, receiver, permit, timeout)
             .await
-            .expect("Failed to receive reply to RemoveAll command from store")
+    }
+
+    /// Atomically removes all the data referenced by the provided keys, and returns
+    /// those of the keys that held a value, in the given order.
+    pub async fn remove_all_existing(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<Vec<Key>> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::DeleteAllExisting(keys, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Removes the key, provided that it holds the `expected` value. Returns whether it
+    /// was removed. E.g. a lock is released only by its current owner, if the holder is
+    /// stored as the value of the lock.
+    ///
+    /// The values are compared in their serialized form, and the check and the delete
+    /// are performed as a single command of the store.
+    pub async fn compare_and_delete(&self, key: Key, expected: Value) -> StoreResult<bool> {
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::CompareAndDelete(key, expected, sender);
+        self.request(&self.channel, command, receiver).await
     }
 
     pub async fn read(&self, key: Key) -> StoreResult<Option<Value>> {
         let (sender, receiver) = oneshot::channel();
-        if let Err(e) = self.channel.send(StoreCommand::Read(key, sender)).await {
-            panic!("Failed to send Read command to store: {e}");
-        }
-        receiver
-            .await
-            .expect("Failed to receive reply to Read command from store")
+        let command = StoreCommand::Read(key, sender);
+        self.request(self.read_channel(), command, receiver).await
     }
 
     /// Fetches all the values for the provided keys.
@@ -199,30 +1529,332 @@ where
         &self,
         keys: impl IntoIterator<Item = Key>,
     ) -> StoreResult<Vec<Option<Value>>> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
         let (sender, receiver) = oneshot::channel();
-        if let Err(e) = self
-            .channel
-            .send(StoreCommand::ReadAll(keys.into_iter().collect(), sender))
+        let command = StoreCommand::ReadAll(keys, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Returns, for each of the provided keys, whether the store holds a value for it.
+    pub async fn contains_all(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<Vec<bool>> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::ContainsAll(keys, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Reads the value for the provided key, giving up with `StoreError::Deadline`
+    /// if the read has not been serviced by the given deadline. This bounds the time
+    /// a read can spend queued behind other commands (e.g. a large `write_all`).
+    pub async fn read_with_deadline(
+        &self,
+        key: Key,
+        deadline: Instant,
+    ) -> StoreResult<Option<Value>> {
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::ReadWithDeadline(key, deadline, sender);
+        let request = self.request(self.read_channel(), command, receiver);
+        tokio::time::timeout_at(deadline, request)
             .await
-        {
-            panic!("Failed to send ReadAll command to store: {e}");
+            .unwrap_or(Err(StoreError::Deadline))
+    }
+
+    /// Fetches all the values for the provided keys, giving up with
+    /// `StoreError::Deadline` if the read has not been serviced by the given deadline.
+    pub async fn read_all_with_deadline(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+        deadline: Instant,
+    ) -> StoreResult<Vec<Option<Value>>> {
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
         }
-        receiver
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::ReadAllWithDeadline(keys, deadline, sender);
+        let request = self.request(self.read_channel(), command, receiver);
+        tokio::time::timeout_at(deadline, request)
+            .await
+            .unwrap_or(Err(StoreError::Deadline))
+    }
+
+    /// Fetches a page of at most `limit` entries whose key starts with `prefix`, and
+    /// which come strictly after the key `after` (from the start of the prefix if `None`).
+    /// Returns the page along with the cursor to pass as `after` for the next page, which
+    /// is `None` once the prefix is exhausted. See `DBMap::prefix_page`, including for
+    /// the `StoreError::InvalidScanBounds` of a `limit` of 0.
+    pub async fn scan_prefix_page<P: Serialize>(
+        &self,
+        prefix: P,
+        after: Option<Key>,
+        limit: usize,
+    ) -> StoreResult<rocks::PrefixPage<Key, Value>> {
+        let prefix = rocks::be_fix_int_ser(&prefix)?;
+        let after = after.as_ref().map(rocks::be_fix_int_ser).transpose()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::ScanPrefixPage(prefix, after, limit, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Fetches the entries with keys in `[start, end)`, sorted in ascending key order,
+    /// i.e. in the order of the serialized keys, which RocksDB iterates in. This is the
+    /// natural order of e.g. unsigned integers and tuples of them, see
+    /// `DBMap::validate_key_ordering`.
+    pub async fn read_range_ordered(&self, start: Key, end: Key) -> StoreResult<Vec<(Key, Value)>> {
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::ReadRangeOrdered(start, end, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Scans the entries with keys in the range `[start, end)` of the options, in the
+    /// order and up to the limit of the options. The bounds are compared in their
+    /// serialized form, as the keys are ordered (see `DBMap::validate_key_ordering`), and a
+    /// `start` greater than the `end` fails with `StoreError::InvalidScanBounds`.
+    pub async fn scan(&self, opts: ScanOpts<Key>) -> StoreResult<Vec<(Key, Value)>> {
+        let start = opts.start.as_ref().map(rocks::be_fix_int_ser).transpose()?;
+        let end = opts.end.as_ref().map(rocks::be_fix_int_ser).transpose()?;
+        if let (Some(start), Some(end)) = (&start, &end) {
+            if start > end {
+                return Err(StoreError::InvalidScanBounds);
+            }
+        }
+        let raw_opts = ScanOpts {
+            start,
+            end,
+            limit: opts.limit,
+            reverse: opts.reverse,
+            readahead_size: opts.readahead_size,
+        };
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::Scan(raw_opts, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Same as `scan_prefix_page`, but resumes the scan at a `Cursor`, and returns the
+    /// cursor of the next page (`None` once the prefix is exhausted), which can be turned
+    /// into a token to resume the scan later on.
+    pub async fn scan_prefix_cursor<P: Serialize>(
+        &self,
+        prefix: P,
+        cursor: Cursor<Key>,
+        limit: usize,
+    ) -> StoreResult<(Vec<(Key, Value)>, Option<Cursor<Key>>)> {
+        let prefix = rocks::be_fix_int_ser(&prefix)?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::ScanPrefixPage(prefix, cursor.into_raw(), limit, sender);
+        let (page, next) = self.request(self.read_channel(), command, receiver).await?;
+        let next = next.as_ref().map(Cursor::after).transpose()?;
+        Ok((page, next))
+    }
+
+    /// Checks that the background task is alive and responsive, by round-tripping a
+    /// command through it within the given timeout, which the task serves with a read of
+    /// the database. A database that hangs or fails to read also fails the check.
+    ///
+    /// Returns `StoreError::StoreClosed` if the task has terminated, and
+    /// `StoreError::Timeout` if it did not reply in time.
+    pub async fn health_check(&self, timeout: Duration) -> StoreResult<()> {
+        let (sender, receiver) = oneshot::channel();
+        let request = self.request(&self.channel, StoreCommand::Ping(sender), receiver);
+        tokio::time::timeout(timeout, request)
+            .await
+            .unwrap_or(Err(StoreError::Timeout))
+    }
+
+    /// Flushes the memtables to disk, fails the pending `notify_read`s with
+    /// `StoreError::StoreClosed`, then terminates the background task and releases the
+    /// database. Once this returns successfully, everything written through the store is
+    /// on disk.
+    ///
+    /// Commands sent afterwards through other clones of this store fail with
+    /// `StoreError::StoreClosed`, except `write` and `remove`, which are dropped.
+    pub async fn flush_and_close(self) -> StoreResult<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.request(&self.channel, StoreCommand::FlushAndClose(sender), receiver)
             .await
-            .expect("Failed to receive reply to ReadAll command from store")
     }
 
     pub async fn notify_read(&self, key: Key) -> StoreResult<Option<Value>> {
+        if !self.notify_read_enabled {
+            return Err(StoreError::NotifyReadDisabled);
+        }
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::NotifyRead(key, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// The version of the table: a counter increased by every mutation applied to the
+    /// store, and by its restarts, which is the cheapest way to find out whether anything
+    /// changed since the version was last read. It must be enabled with
+    /// `StoreConfig::track_version`, and otherwise fails with
+    /// `StoreError::VersionNotTracked`.
+    ///
+    /// The version is increased by the command applying the mutation, so a later command
+    /// never observes the mutation without the new version. It is written by the same
+    /// batch as the mutation, so a mutation that fails leaves it unchanged.
+    pub async fn version(&self) -> StoreResult<u64> {
         let (sender, receiver) = oneshot::channel();
-        if let Err(e) = self
+        self.request(&self.channel, StoreCommand::Version(sender), receiver)
+            .await
+    }
+
+    /// Reads the key, and subscribes to its updates at the same point of the store's
+    /// history: the watch receives every update applied after the read, and none
+    /// before it, e.g. to maintain a replica of the key.
+    pub async fn get_and_watch(&self, key: Key) -> StoreResult<WatchedValue<Key, Value>> {
+        let key_buf = rocks::be_fix_int_ser(&key)?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::GetAndWatch(key, key_buf, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Reads the key, and on a miss, loads its value with `loader`, writes it, and returns
+    /// it: the store then serves as a cache in front of the source of the values.
+    ///
+    /// The concurrent misses of a key only run a single loader, whose outcome (the value
+    /// or its error) is shared by all of them: the others are dropped without being run.
+    /// If the caller running the loader goes away before it completes, one of the
+    /// waiting callers runs its own loader instead.
+    pub async fn get_or_populate<F>(&self, key: Key, loader: F) -> StoreResult<Value>
+    where
+        Value: Clone,
+        F: Future<Output = StoreResult<Value>>,
+    {
+        let mut key = key;
+        loop {
+            let (sender, receiver) = oneshot::channel();
+            let command = StoreCommand::ReadOrClaim(key, sender);
+            match self.request(&self.channel, command, receiver).await? {
+                Claim::Present(value) => return Ok(value),
+                Claim::Wait(waiting_key, mut results) => match results.recv().await {
+                    Ok(result) => return result,
+                    // the loading caller went away
+                    Err(_) => key = waiting_key,
+                },
+                Claim::Load(loading_key, results) => {
+                    let loaded = loader.await;
+                    let response = self.populate(loading_key, loaded).await;
+                    let _ = results.send(response.clone());
+                    return response;
+                }
+            }
+        }
+    }
+
+    async fn populate(&self, key: Key, loaded: StoreResult<Value>) -> StoreResult<Value> {
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::Populate(key, loaded, sender);
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Waits until a value is written for any of the keys, and returns the key written
+    /// first along with its value. If some of the keys already hold a value, the first
+    /// of them (in the given order) is returned right away. Unlike with `notify_read`,
+    /// deleting a key does not resolve this, and with no keys this never returns.
+    ///
+    /// Once it resolves, nothing remains registered on the other keys.
+    pub async fn notify_read_any(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<(Key, Value)> {
+        if !self.notify_read_enabled {
+            return Err(StoreError::NotifyReadDisabled);
+        }
+        let keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() {
+            return std::future::pending().await;
+        }
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::NotifyReadAny(keys, sender);
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Like `write`, with the command processed in a span carrying `request_id`.
+    pub async fn write_traced(&self, key: Key, value: Value, request_id: impl Into<String>) {
+        let command = StoreCommand::Write(key, value);
+        if self
             .channel
-            .send(StoreCommand::NotifyRead(key, sender))
+            .send(StoreCommand::Traced(request_id.into(), Box::new(command)))
             .await
+            .is_err()
         {
-            panic!("Failed to send NotifyRead command to store: {e}");
+            assert!(
+                !matches!(self.task_error().await, StoreError::BackgroundTaskPanicked),
+                "The background task of the store panicked"
+            );
+            // the command is unacknowledged: once the store is closed, it is dropped
+            tracing::warn!("Dropped a Write command sent to a closed store");
+        }
+    }
+
+    /// Like `write_all`, with the command processed in a span carrying `request_id`.
+    pub async fn write_all_traced(
+        &self,
+        key_value_pairs: impl IntoIterator<Item = (Key, Value)>,
+        request_id: impl Into<String>,
+    ) -> StoreResult<()> {
+        let key_values: Vec<_> = key_value_pairs.into_iter().collect();
+        if key_values.is_empty() {
+            return Ok(());
         }
-        receiver
+        let _permit = self.acquire_in_flight()?;
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::WriteAll(key_values, sender);
+        let command = StoreCommand::Traced(request_id.into(), Box::new(command));
+        self.request(&self.channel, command, receiver).await
+    }
+
+    /// Like `remove`, with the command processed in a span carrying `request_id`.
+    pub async fn remove_traced(&self, key: Key, request_id: impl Into<String>) {
+        let command = StoreCommand::Delete(key);
+        if self
+            .channel
+            .send(StoreCommand::Traced(request_id.into(), Box::new(command)))
             .await
-            .expect("Failed to receive reply to NotifyRead command from store")
+            .is_err()
+        {
+            assert!(
+                !matches!(self.task_error().await, StoreError::BackgroundTaskPanicked),
+                "The background task of the store panicked"
+            );
+            // the command is unacknowledged: once the store is closed, it is dropped
+            tracing::warn!("Dropped a Delete command sent to a closed store");
+        }
+    }
+
+    /// Like `read`, with the command processed in a span carrying `request_id`.
+    pub async fn read_traced(
+        &self,
+        key: Key,
+        request_id: impl Into<String>,
+    ) -> StoreResult<Option<Value>> {
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::Read(key, sender);
+        let command = StoreCommand::Traced(request_id.into(), Box::new(command));
+        self.request(self.read_channel(), command, receiver).await
+    }
+
+    /// Like `notify_read`, with the command processed in a span carrying `request_id`.
+    pub async fn notify_read_traced(
+        &self,
+        key: Key,
+        request_id: impl Into<String>,
+    ) -> StoreResult<Option<Value>> {
+        if !self.notify_read_enabled {
+            return Err(StoreError::NotifyReadDisabled);
+        }
+        let (sender, receiver) = oneshot::channel();
+        let command = StoreCommand::NotifyRead(key, sender);
+        let command = StoreCommand::Traced(request_id.into(), Box::new(command));
+        self.request(self.read_channel(), command, receiver).await
     }
 }
@@ -0,0 +1,96 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// The upper bounds of the buckets of the `notify_read` wait histogram. Waits longer
+/// than the last bound are counted in an extra, unbounded, bucket.
+pub const NOTIFY_READ_WAIT_BUCKETS: [Duration; 8] = [
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(30),
+];
+
+/// Metrics collected by a `Store` about the `notify_read`s it serves.
+///
+/// A `notify_read` is either satisfied immediately, when the value is already present
+/// on registration, or waits until the value is written (or deleted). For the latter,
+/// the time from registration to fulfillment is recorded in a histogram.
+///
+/// The metrics are shared by all the clones of a store, and can be handed out to an
+/// exporter through `Store::metrics`.
+#[derive(Debug, Default)]
+pub struct StoreMetrics {
+    notify_read_immediate: AtomicU64,
+    notify_read_waited: AtomicU64,
+    notify_read_wait_micros: AtomicU64,
+    notify_read_wait_histogram: [AtomicU64; NOTIFY_READ_WAIT_BUCKETS.len() + 1],
+    notify_read_pending: AtomicU64,
+}
+
+impl StoreMetrics {
+    pub(crate) fn record_notify_read_immediate(&self) {
+        self.notify_read_immediate.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_notify_read_registered(&self) {
+        self.notify_read_pending.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_notify_reads_released(&self, count: usize) {
+        self.notify_read_pending
+            .fetch_sub(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_notify_read_wait(&self, wait: Duration) {
+        self.notify_read_waited.fetch_add(1, Ordering::Relaxed);
+        self.notify_read_wait_micros
+            .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+        let bucket = NOTIFY_READ_WAIT_BUCKETS
+            .iter()
+            .position(|bound| wait <= *bound)
+            .unwrap_or(NOTIFY_READ_WAIT_BUCKETS.len());
+        self.notify_read_wait_histogram[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of `notify_read`s satisfied on registration.
+    pub fn notify_read_immediate(&self) -> u64 {
+        self.notify_read_immediate.load(Ordering::Relaxed)
+    }
+
+    /// The number of `notify_read`s that had to wait before being satisfied.
+    pub fn notify_read_waited(&self) -> u64 {
+        self.notify_read_waited.load(Ordering::Relaxed)
+    }
+
+    /// The number of `notify_read`s currently waiting, including those whose caller
+    /// has gone away but which have not been cleaned up yet.
+    pub fn notify_read_pending(&self) -> u64 {
+        self.notify_read_pending.load(Ordering::Relaxed)
+    }
+
+    /// The total time spent waiting by the `notify_read`s counted in `notify_read_waited`.
+    pub fn notify_read_wait_total(&self) -> Duration {
+        Duration::from_micros(self.notify_read_wait_micros.load(Ordering::Relaxed))
+    }
+
+    /// The count of each bucket of the wait histogram, as `(upper bound, count)` pairs,
+    /// the last bucket being unbounded (`None`).
+    pub fn notify_read_wait_histogram(&self) -> Vec<(Option<Duration>, u64)> {
+        NOTIFY_READ_WAIT_BUCKETS
+            .iter()
+            .copied()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .zip(self.notify_read_wait_histogram.iter())
+            .map(|(bound, count)| (bound, count.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
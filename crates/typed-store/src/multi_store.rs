@@ -0,0 +1,244 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{rocks, traits::Map, CatchPanic, StoreError, StoreResult, COMMAND_CHANNEL_CAPACITY};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::{
+    mpsc::{channel, Sender},
+    oneshot, Semaphore,
+};
+
+pub enum MultiStoreCommand<Key, Value> {
+    Write(String, Key, Value),
+    WriteAll(String, Vec<(Key, Value)>, oneshot::Sender<StoreResult<()>>),
+    Delete(String, Key),
+    DeleteAll(String, Vec<Key>, oneshot::Sender<StoreResult<()>>),
+    Read(String, Key, oneshot::Sender<StoreResult<Option<Value>>>),
+    ReadAll(
+        String,
+        Vec<Key>,
+        oneshot::Sender<StoreResult<Vec<Option<Value>>>>,
+    ),
+}
+
+/// A single async handle over several `DBMap`s, each registered under a table name.
+///
+/// All the tables are served by one background task, so a `MultiStore` replaces
+/// one `Store` (and one task) per table. Every operation names the table it
+/// targets; naming a table that was not registered returns
+/// `TypedStoreError::UnregisteredColumn`.
+///
+/// All the tables share the key and value types of the store: tables of other types
+/// need a `MultiStore` (or a `Store`) of their own.
+///
+/// Atomicity: `write_all` and `remove_all` are atomic within the table they target.
+/// Operations on different tables are applied in the order they are received, but are
+/// never atomic with respect to each other, even if the tables share a database.
+pub struct MultiStore<K, V> {
+    channel: Sender<MultiStoreCommand<K, V>>,
+    tables: Arc<HashSet<String>>,
+    /// set once the background task has panicked
+    panicked: Arc<AtomicBool>,
+    /// closed once the background task is dropped, after `panicked` is set
+    terminated: Arc<Semaphore>,
+}
+
+// not derived, which would require the keys and values to be Clone
+impl<K, V> Clone for MultiStore<K, V> {
+    fn clone(&self) -> Self {
+        MultiStore {
+            channel: self.channel.clone(),
+            tables: self.tables.clone(),
+            panicked: self.panicked.clone(),
+            terminated: self.terminated.clone(),
+        }
+    }
+}
+
+impl<Key, Value> MultiStore<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send + 'static,
+    Value: Serialize + DeserializeOwned + Send + 'static,
+{
+    pub fn new(tables: impl IntoIterator<Item = (String, rocks::DBMap<Key, Value>)>) -> Self {
+        let keyed_dbs: HashMap<String, rocks::DBMap<Key, Value>> = tables.into_iter().collect();
+        let table_names = keyed_dbs.keys().cloned().collect();
+        let (tx, mut rx) = channel(COMMAND_CHANNEL_CAPACITY);
+        let panicked = Arc::new(AtomicBool::new(false));
+        let terminated = Arc::new(Semaphore::new(0));
+        let task = async move {
+            let table = |name: &str| {
+                keyed_dbs
+                    .get(name)
+                    .ok_or_else(|| StoreError::UnregisteredColumn(name.to_string()))
+            };
+            while let Some(command) = rx.recv().await {
+                match command {
+                    MultiStoreCommand::Write(name, key, value) => {
+                        if let Ok(keyed_db) = table(&name) {
+                            let _ = keyed_db.insert(&key, &value);
+                        }
+                    }
+                    MultiStoreCommand::WriteAll(name, key_values, sender) => {
+                        let response = table(&name).and_then(|keyed_db| {
+                            keyed_db.multi_insert(key_values.iter().map(|(k, v)| (k, v)))
+                        });
+                        let _ = sender.send(response);
+                    }
+                    MultiStoreCommand::Delete(name, key) => {
+                        if let Ok(keyed_db) = table(&name) {
+                            let _ = keyed_db.remove(&key);
+                        }
+                    }
+                    MultiStoreCommand::DeleteAll(name, keys, sender) => {
+                        let response =
+                            table(&name).and_then(|keyed_db| keyed_db.multi_remove(keys.iter()));
+                        let _ = sender.send(response);
+                    }
+                    MultiStoreCommand::Read(name, key, sender) => {
+                        let response = table(&name).and_then(|keyed_db| keyed_db.get(&key));
+                        let _ = sender.send(response);
+                    }
+                    MultiStoreCommand::ReadAll(name, keys, sender) => {
+                        let response =
+                            table(&name).and_then(|keyed_db| keyed_db.multi_get(keys.as_slice()));
+                        let _ = sender.send(response);
+                    }
+                }
+            }
+        };
+        tokio::spawn(CatchPanic {
+            task: Box::pin(task),
+            panicked: panicked.clone(),
+            terminated: terminated.clone(),
+        });
+        Self {
+            channel: tx,
+            tables: Arc::new(table_names),
+            panicked,
+            terminated,
+        }
+    }
+}
+
+impl<Key, Value> MultiStore<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send,
+    Value: Serialize + DeserializeOwned + Send,
+{
+    /// Returns true if the background task of the store panicked. The store can't serve
+    /// any command afterwards, and must be recreated.
+    pub fn has_panicked(&self) -> bool {
+        self.panicked.load(Ordering::SeqCst)
+    }
+
+    /// The error of a command the background task did not serve, as it has terminated:
+    /// `StoreError::BackgroundTaskPanicked` if it panicked, `StoreError::StoreClosed`
+    /// otherwise.
+    async fn task_error(&self) -> StoreError {
+        // nothing ever adds a permit: this waits for the task to be dropped
+        let _ = self.terminated.acquire().await;
+        if self.has_panicked() {
+            StoreError::BackgroundTaskPanicked
+        } else {
+            StoreError::StoreClosed
+        }
+    }
+
+    /// Sends the command to the background task, failing with `task_error` if it has
+    /// terminated.
+    async fn send(&self, command: MultiStoreCommand<Key, Value>) -> StoreResult<()> {
+        if self.channel.send(command).await.is_err() {
+            return Err(self.task_error().await);
+        }
+        Ok(())
+    }
+
+    /// Sends the command to the background task, and waits for its reply on `receiver`.
+    async fn request<T>(
+        &self,
+        command: MultiStoreCommand<Key, Value>,
+        receiver: oneshot::Receiver<StoreResult<T>>,
+    ) -> StoreResult<T> {
+        self.send(command).await?;
+        match receiver.await {
+            Ok(reply) => reply,
+            Err(_) => Err(self.task_error().await),
+        }
+    }
+
+    fn check_table(&self, table: &str) -> StoreResult<()> {
+        if self.tables.contains(table) {
+            Ok(())
+        } else {
+            Err(StoreError::UnregisteredColumn(table.to_string()))
+        }
+    }
+
+    pub async fn write(&self, table: &str, key: Key, value: Value) -> StoreResult<()> {
+        self.check_table(table)?;
+        self.send(MultiStoreCommand::Write(table.to_string(), key, value))
+            .await
+    }
+
+    /// Atomically writes all the key-value pairs in the given table.
+    pub async fn write_all(
+        &self,
+        table: &str,
+        key_value_pairs: impl IntoIterator<Item = (Key, Value)>,
+    ) -> StoreResult<()> {
+        self.check_table(table)?;
+        let (sender, receiver) = oneshot::channel();
+        let command = MultiStoreCommand::WriteAll(
+            table.to_string(),
+            key_value_pairs.into_iter().collect(),
+            sender,
+        );
+        self.request(command, receiver).await
+    }
+
+    pub async fn remove(&self, table: &str, key: Key) -> StoreResult<()> {
+        self.check_table(table)?;
+        self.send(MultiStoreCommand::Delete(table.to_string(), key))
+            .await
+    }
+
+    /// Atomically removes all the data referenced by the provided keys in the given table.
+    pub async fn remove_all(
+        &self,
+        table: &str,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<()> {
+        self.check_table(table)?;
+        let (sender, receiver) = oneshot::channel();
+        let command =
+            MultiStoreCommand::DeleteAll(table.to_string(), keys.into_iter().collect(), sender);
+        self.request(command, receiver).await
+    }
+
+    pub async fn read(&self, table: &str, key: Key) -> StoreResult<Option<Value>> {
+        self.check_table(table)?;
+        let (sender, receiver) = oneshot::channel();
+        let command = MultiStoreCommand::Read(table.to_string(), key, sender);
+        self.request(command, receiver).await
+    }
+
+    /// Fetches all the values for the provided keys in the given table.
+    pub async fn read_all(
+        &self,
+        table: &str,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<Vec<Option<Value>>> {
+        self.check_table(table)?;
+        let (sender, receiver) = oneshot::channel();
+        let command =
+            MultiStoreCommand::ReadAll(table.to_string(), keys.into_iter().collect(), sender);
+        self.request(command, receiver).await
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{Store, StoreResult};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A read-only handle to a `Store`, for the components that must not write to it.
+/// It is obtained with `Store::reader`, and sends its commands to the same background
+/// task as the store.
+///
+/// The write operations are not available on a reader:
+/// ```compile_fail
+/// # use typed_store::StoreReader;
+/// async fn write(reader: StoreReader<u32, u32>) {
+///     reader.write(1, 1).await;
+/// }
+/// ```
+pub struct StoreReader<K, V> {
+    store: Store<K, V>,
+}
+
+// not derived, which would require the keys and values to be Clone
+impl<K, V> Clone for StoreReader<K, V> {
+    fn clone(&self) -> Self {
+        StoreReader {
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<Key, Value> StoreReader<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send,
+    Value: Serialize + DeserializeOwned + Send,
+{
+    pub(crate) fn new(store: Store<Key, Value>) -> Self {
+        StoreReader { store }
+    }
+
+    pub async fn read(&self, key: Key) -> StoreResult<Option<Value>> {
+        self.store.read(key).await
+    }
+
+    /// Fetches all the values for the provided keys.
+    pub async fn read_all(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<Vec<Option<Value>>> {
+        self.store.read_all(keys).await
+    }
+
+    /// Returns, for each of the provided keys, whether the store holds a value for it.
+    pub async fn contains_all(
+        &self,
+        keys: impl IntoIterator<Item = Key>,
+    ) -> StoreResult<Vec<bool>> {
+        self.store.contains_all(keys).await
+    }
+
+    /// Waits until a value is written for the key, see `Store::notify_read`.
+    pub async fn notify_read(&self, key: Key) -> StoreResult<Option<Value>> {
+        self.store.notify_read(key).await
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use rocksdb::WriteBatch;
+use serde::Serialize;
+
+use super::{be_fix_int_ser, DBMap, TypedStoreError};
+
+/// Streams writes into a `DBMap`, accumulating them in a batch that is committed
+/// whenever it reaches `batch_size` operations.
+///
+/// Larger batches amortize the cost of each write to the database during bulk loads,
+/// at the cost of the memory held by the pending batch. Each commit is atomic, but the
+/// stream as a whole is not: on failure, the operations of the previous commits stay
+/// written. The operations still pending are committed by `finish`, and discarded if
+/// the writer is dropped instead.
+pub struct BatchWriter<'a, K, V> {
+    map: &'a DBMap<K, V>,
+    batch: WriteBatch,
+    batch_size: usize,
+    commits: usize,
+}
+
+impl<'a, K: Serialize, V: Serialize> BatchWriter<'a, K, V> {
+    pub fn new(map: &'a DBMap<K, V>, batch_size: usize) -> Self {
+        BatchWriter {
+            map,
+            batch: WriteBatch::default(),
+            batch_size: batch_size.max(1),
+            commits: 0,
+        }
+    }
+
+    /// Queues the insertion of the key-value pair.
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<(), TypedStoreError> {
+        let key_buf = be_fix_int_ser(key)?;
+        let value_buf = bincode::serialize(value)?;
+        self.batch.put_cf(&self.map.cf(), key_buf, value_buf);
+        self.commit_if_full()
+    }
+
+    /// Queues the removal of the key.
+    pub fn remove(&mut self, key: &K) -> Result<(), TypedStoreError> {
+        let key_buf = be_fix_int_ser(key)?;
+        self.batch.delete_cf(&self.map.cf(), key_buf);
+        self.commit_if_full()
+    }
+
+    /// Commits the pending operations now, whether the batch is full or not.
+    pub fn flush_batch(&mut self) -> Result<(), TypedStoreError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut self.batch);
+        self.map.rocksdb.write(batch)?;
+        self.commits += 1;
+        Ok(())
+    }
+
+    /// Commits the pending operations, and returns the number of commits performed.
+    pub fn finish(mut self) -> Result<usize, TypedStoreError> {
+        self.flush_batch()?;
+        Ok(self.commits)
+    }
+
+    /// The number of operations pending in the batch.
+    pub fn pending(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// The number of batches committed so far.
+    pub fn commits(&self) -> usize {
+        self.commits
+    }
+
+    fn commit_if_full(&mut self) -> Result<(), TypedStoreError> {
+        if self.batch.len() >= self.batch_size {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+/// A key made of two parts, stored as the serialization of `first` immediately followed
+/// by the serialization of `second`. All the keys sharing their first part are therefore
+/// contiguous in a `DBMap`, sorted by their second part, and can be scanned with
+/// `DBMap::prefix_iter(&CompositeKey::prefix(first))`.
+///
+/// Keys are serialized in big-endian order, so that numeric parts sort numerically.
+/// A variable-size first part (e.g. a `String`) is stored with its length, so that
+/// the prefix of `"ab"` does not match the keys of `"abc"`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CompositeKey<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A, B> CompositeKey<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        CompositeKey { first, second }
+    }
+
+    /// The scan prefix of all the keys whose first part is `first`.
+    pub fn prefix(first: A) -> CompositePrefix<A, B> {
+        CompositePrefix {
+            first,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// The prefix of the `CompositeKey`s sharing a first part, which serializes exactly as
+/// the leading bytes of these keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompositePrefix<A, B> {
+    first: A,
+    _phantom: PhantomData<fn() -> B>,
+}
+
+impl<A: Serialize, B> Serialize for CompositePrefix<A, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.first.serialize(serializer)
+    }
+}
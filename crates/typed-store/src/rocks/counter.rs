@@ -0,0 +1,69 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use rocksdb::MergeOperands;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, DBMap, DBMapOptions, TypedStoreError};
+use crate::traits::Map;
+
+/// The name under which the merge operator of the counters is registered.
+const COUNTER_MERGE_OPERATOR: &str = "typed_store_counter";
+
+/// A map of `i64` counters, each incremented atomically with a RocksDB merge rather
+/// than with a read followed by a write, so that concurrent increments are never lost.
+///
+/// The database must be opened with options carrying the counters' merge operator,
+/// see `DBMapOptions::with_counter_merge_operator`.
+pub struct Counter<K> {
+    map: DBMap<K, i64>,
+}
+
+impl<K> Counter<K> {
+    pub fn new(map: DBMap<K, i64>) -> Self {
+        Counter { map }
+    }
+}
+
+impl<K: Serialize + DeserializeOwned> Counter<K> {
+    /// Adds `delta` (possibly negative) to the counter of the key, starting from 0.
+    pub fn increment(&self, key: &K, delta: i64) -> Result<(), TypedStoreError> {
+        let key_buf = be_fix_int_ser(key)?;
+        // the counters are stored in bincode's encoding of an i64
+        self.map
+            .rocksdb
+            .merge_cf(&self.map.cf(), &key_buf, delta.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Returns the value of the counter of the key, 0 if it was never incremented.
+    pub fn get(&self, key: &K) -> Result<i64, TypedStoreError> {
+        Ok(self.map.get(key)?.unwrap_or_default())
+    }
+
+    /// Resets the counter of the key to 0.
+    pub fn reset(&self, key: &K) -> Result<(), TypedStoreError> {
+        self.map.remove(key)
+    }
+}
+
+impl DBMapOptions {
+    /// Registers the merge operator that `Counter` relies on.
+    pub fn with_counter_merge_operator(self) -> Self {
+        self.with_merge_operator(COUNTER_MERGE_OPERATOR, counter_merge)
+    }
+}
+
+/// Sums the existing value of a counter and its pending increments. A malformed
+/// operand fails the merge, which RocksDB reports as a corruption.
+fn counter_merge(
+    _key: &[u8],
+    existing_value: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let decode = |bytes: &[u8]| Some(i64::from_le_bytes(bytes.try_into().ok()?));
+    let mut total = existing_value.map_or(Some(0), decode)?;
+    for operand in operands {
+        total = total.wrapping_add(decode(operand)?);
+    }
+    Some(total.to_le_bytes().to_vec())
+}
@@ -0,0 +1,38 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The stable discriminants of a fieldless enum used in keys, see `EnumKey`.
+pub trait KeyDiscriminant: Sized {
+    /// The byte the variant is stored as. It must be unique to the variant, and must not
+    /// change once data is stored.
+    fn discriminant(&self) -> u8;
+
+    /// The variant stored as the given byte, if any.
+    fn from_discriminant(discriminant: u8) -> Option<Self>;
+}
+
+/// A fieldless enum stored in keys as the single byte of its explicit discriminant,
+/// rather than as its (4 bytes) variant index, which follows the order of declaration
+/// and changes when variants are reordered.
+///
+/// The keys of a variant sort in the order of its discriminant, and a table can be
+/// partitioned by variant by keying it with a `CompositeKey<EnumKey<E>, _>`: the entries
+/// of each variant are then scanned with `DBMap::prefix_iter(&CompositeKey::prefix(..))`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EnumKey<E>(pub E);
+
+impl<E: KeyDiscriminant> Serialize for EnumKey<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.0.discriminant())
+    }
+}
+
+impl<'de, E: KeyDiscriminant> Deserialize<'de> for EnumKey<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let discriminant = u8::deserialize(deserializer)?;
+        E::from_discriminant(discriminant)
+            .map(EnumKey)
+            .ok_or_else(|| D::Error::custom(format!("unknown discriminant {discriminant}")))
+    }
+}
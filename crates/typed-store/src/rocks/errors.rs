@@ -19,6 +19,36 @@ pub enum TypedStoreError {
     UnregisteredColumn(String),
     #[error("a batch operation can't operate across databases")]
     CrossDBBatch,
+    #[error("a snapshot can't read from another database")]
+    CrossDBSnapshot,
+    #[error("the deadline elapsed before the operation was serviced")]
+    Deadline,
+    #[error("the operation was not acknowledged within its timeout")]
+    Timeout,
+    #[error("I/O error: {0}")]
+    IOError(String),
+    #[error("the store is closed")]
+    StoreClosed,
+    #[error("too many writes are in flight")]
+    Overloaded,
+    #[error("the background task of the store panicked")]
+    BackgroundTaskPanicked,
+    #[error("a key to insert already exists")]
+    KeyExists,
+    #[error("the key encoding does not preserve the key ordering: {0}")]
+    KeyOrdering(String),
+    #[error("no state of the map is retained at sequence number {0}")]
+    SequenceNotRetained(u64),
+    #[error("too many reads are awaiting the key")]
+    TooManyWaiters,
+    #[error("notify_read is disabled on this store")]
+    NotifyReadDisabled,
+    #[error("the store can't accept the operation without waiting")]
+    WouldBlock,
+    #[error("the bounds of the scan are invalid, e.g. its start is past its end")]
+    InvalidScanBounds,
+    #[error("the version of the table is not tracked: {0}")]
+    VersionNotTracked(String),
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug, Error)]
@@ -111,3 +141,9 @@ impl From<bincode::Error> for TypedStoreError {
         TypedStoreError::SerializationError(format!("{err}"))
     }
 }
+
+impl From<std::io::Error> for TypedStoreError {
+    fn from(err: std::io::Error) -> Self {
+        TypedStoreError::IOError(format!("{err}"))
+    }
+}
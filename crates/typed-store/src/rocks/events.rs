@@ -0,0 +1,121 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::mpsc,
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use tracing::warn;
+
+use super::{DBMap, TypedStoreError};
+
+/// A change of the SST files of a column family, as observed by `DBMap::watch_events`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableEvent {
+    /// memtables were flushed into `files` new level-0 files, of `bytes` in total
+    Flush { files: usize, bytes: usize },
+    /// compactions rewrote data into `files` new files of `level`, of `bytes` in total
+    Compaction {
+        level: i32,
+        files: usize,
+        bytes: usize,
+    },
+}
+
+/// Watches the SST files of a column family on a thread of its own, which stops when
+/// the watcher is dropped.
+pub struct EventWatcher {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for EventWatcher {
+    fn drop(&mut self) {
+        // disconnecting the channel wakes the thread up right away
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<K: Send + 'static, V: Send + 'static> DBMap<K, V> {
+    /// Calls `callback` with the flushes and compactions of this map's column family,
+    /// e.g. to update derived state or record metrics once they complete.
+    ///
+    /// The RocksDB version in use doesn't expose event listeners: the SST files of the
+    /// column family are instead polled every `interval`, and the files that appeared
+    /// since the previous poll are reported, grouped by level. The events are thus
+    /// delayed by up to `interval`, and the flushes and compactions completing between
+    /// two polls are merged. Moving a file to another level without rewriting it is not
+    /// reported. The database stays open while the watcher is alive.
+    pub fn watch_events<F>(
+        &self,
+        interval: Duration,
+        mut callback: F,
+    ) -> Result<EventWatcher, TypedStoreError>
+    where
+        F: FnMut(TableEvent) + Send + 'static,
+    {
+        let map = self.clone();
+        let mut known = map.sst_file_names()?;
+        let (stop, stopped) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("typed-store-events".to_string())
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    match map.poll_events(&mut known) {
+                        Ok(events) => events.into_iter().for_each(&mut callback),
+                        Err(e) => warn!("Failed to list the SST files of {}: {e}", map.cf),
+                    }
+                }
+            })?;
+        Ok(EventWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    fn sst_file_names(&self) -> Result<HashSet<String>, TypedStoreError> {
+        Ok(self
+            .rocksdb
+            .live_files()?
+            .into_iter()
+            .filter(|file| file.column_family_name == self.cf)
+            .map(|file| file.name)
+            .collect())
+    }
+
+    /// Reports the files not in `known`, which is updated to the current files.
+    fn poll_events(&self, known: &mut HashSet<String>) -> Result<Vec<TableEvent>, TypedStoreError> {
+        let files: Vec<_> = self
+            .rocksdb
+            .live_files()?
+            .into_iter()
+            .filter(|file| file.column_family_name == self.cf)
+            .collect();
+
+        // (files, bytes) per level
+        let mut added: BTreeMap<i32, (usize, usize)> = BTreeMap::new();
+        for file in files.iter().filter(|file| !known.contains(&file.name)) {
+            let (count, bytes) = added.entry(file.level).or_default();
+            *count += 1;
+            *bytes += file.size;
+        }
+        *known = files.into_iter().map(|file| file.name).collect();
+
+        Ok(added
+            .into_iter()
+            .map(|(level, (files, bytes))| match level {
+                0 => TableEvent::Flush { files, bytes },
+                level => TableEvent::Compaction {
+                    level,
+                    files,
+                    bytes,
+                },
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::collections::BTreeMap;
+
+use rocksdb::{DBWithThreadMode, MultiThreaded, SnapshotWithThreadMode};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, DBMap, TypedStoreError};
+
+type Snapshot<'a> = SnapshotWithThreadMode<'a, DBWithThreadMode<MultiThreaded>>;
+
+/// The past states of a `DBMap`, captured at the sequence numbers of the database, which
+/// can be read back for debugging or auditing.
+///
+/// RocksDB only retains the overwritten versions of a key as long as a snapshot needs
+/// them: compactions discard the others. A state can thus only be read at a sequence
+/// number that was captured, and the history window is bounded by `capacity`, the
+/// oldest snapshot being released when a capture exceeds it. Each retained snapshot
+/// keeps the versions written after it alive, which costs space until it is released.
+pub struct SequenceHistory<'a, K, V> {
+    map: &'a DBMap<K, V>,
+    snapshots: BTreeMap<u64, Snapshot<'a>>,
+    capacity: usize,
+}
+
+impl<K, V> DBMap<K, V> {
+    /// The sequence number of the last write to the database, across all the column
+    /// families.
+    pub fn latest_sequence_number(&self) -> u64 {
+        self.rocksdb.latest_sequence_number()
+    }
+
+    /// Returns a history of this map retaining at most `capacity` captured states.
+    pub fn history(&self, capacity: usize) -> SequenceHistory<'_, K, V> {
+        SequenceHistory {
+            map: self,
+            snapshots: BTreeMap::new(),
+            capacity: capacity.max(1),
+        }
+    }
+}
+
+impl<'a, K, V> SequenceHistory<'a, K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Captures the current state of the map, and returns the sequence number it can be
+    /// read back at.
+    pub fn capture(&mut self) -> u64 {
+        // the sequence number of a snapshot isn't exposed: it's known when no write
+        // lands while the snapshot is taken, between two reads of the latest one
+        let (sequence, snapshot) = loop {
+            let before = self.map.latest_sequence_number();
+            let snapshot = self.map.rocksdb.snapshot();
+            if self.map.latest_sequence_number() == before {
+                break (before, snapshot);
+            }
+        };
+        self.snapshots.insert(sequence, snapshot);
+        while self.snapshots.len() > self.capacity {
+            let oldest = *self.snapshots.keys().next().expect("history is not empty");
+            self.snapshots.remove(&oldest);
+        }
+        sequence
+    }
+
+    /// The sequence numbers that can currently be read at, in ascending order.
+    pub fn sequences(&self) -> impl Iterator<Item = u64> + '_ {
+        self.snapshots.keys().copied()
+    }
+
+    /// Returns the value the key had as of the sequence number `sequence`, which must
+    /// have been returned by `capture` and still be retained.
+    pub fn get_at_sequence(&self, key: &K, sequence: u64) -> Result<Option<V>, TypedStoreError> {
+        let snapshot = self
+            .snapshots
+            .get(&sequence)
+            .ok_or(TypedStoreError::SequenceNotRetained(sequence))?;
+        let key_buf = be_fix_int_ser(key)?;
+        match snapshot.get_cf_opt(&self.map.cf(), &key_buf, self.map.read_options())? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, DBBatch, DBMap, TypedStoreError};
+use crate::traits::Map;
+
+/// A map maintaining a secondary index of its entries, e.g. the users by their email
+/// alongside the users by their ID.
+///
+/// The index maps the key derived from each entry back to the key of the entry. It is
+/// updated in the same batch as the entries, so the two are never observed out of sync.
+/// The update reads the entry it replaces to drop its stale index key, so concurrent
+/// writes to the same key must be serialized by the caller.
+///
+/// When several entries share an index key, the index points to the last of them
+/// written, and keeps doing so when the others are updated or removed.
+pub struct IndexedDBMap<K, V, I> {
+    map: DBMap<K, V>,
+    index: DBMap<I, K>,
+    index_key: Box<dyn Fn(&K, &V) -> I + Send + Sync>,
+}
+
+impl<K, V> DBMap<K, V> {
+    /// Maintains `index` as a secondary index of this map, indexing each entry under
+    /// the key computed by `index_key`. Both maps must belong to the same database.
+    pub fn with_index<I>(
+        self,
+        index: DBMap<I, K>,
+        index_key: impl Fn(&K, &V) -> I + Send + Sync + 'static,
+    ) -> IndexedDBMap<K, V, I> {
+        IndexedDBMap {
+            map: self,
+            index,
+            index_key: Box::new(index_key),
+        }
+    }
+}
+
+impl<K, V, I> IndexedDBMap<K, V, I>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    I: Serialize + DeserializeOwned,
+{
+    /// Returns the value for the given key from the map, if it exists.
+    pub fn get(&self, key: &K) -> Result<Option<V>, TypedStoreError> {
+        self.map.get(key)
+    }
+
+    /// Returns the entry indexed under the given index key, if any.
+    pub fn get_by_index(&self, index_key: &I) -> Result<Option<(K, V)>, TypedStoreError> {
+        match self.index.get(index_key)? {
+            Some(key) => Ok(self.map.get(&key)?.map(|value| (key, value))),
+            None => Ok(None),
+        }
+    }
+
+    /// Inserts the key-value pair, along with its index entry, and drops the index
+    /// entry of the value it replaces.
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), TypedStoreError> {
+        let mut batch = DBBatch::new(&self.map.rocksdb);
+        if let Some(previous) = self.map.get(key)? {
+            batch = self.drop_index_entry(batch, key, &previous)?;
+        }
+        batch
+            .insert_batch(&self.index, [((self.index_key)(key, value), key)])?
+            .insert_batch(&self.map, [(key, value)])?
+            .write()
+    }
+
+    /// Removes the entry for the given key, along with its index entry.
+    pub fn remove(&self, key: &K) -> Result<(), TypedStoreError> {
+        match self.map.get(key)? {
+            Some(previous) => self
+                .drop_index_entry(DBBatch::new(&self.map.rocksdb), key, &previous)?
+                .delete_batch(&self.map, [key])?
+                .write(),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds to the batch the deletion of the index entry of `previous`, the value held
+    /// by `key`, unless the entry has since been taken over by another key.
+    fn drop_index_entry(
+        &self,
+        batch: DBBatch,
+        key: &K,
+        previous: &V,
+    ) -> Result<DBBatch, TypedStoreError> {
+        let stale = (self.index_key)(key, previous);
+        match self.index.get(&stale)? {
+            Some(indexed) if be_fix_int_ser(&indexed)? == be_fix_int_ser(key)? => {
+                batch.delete_batch(&self.index, [stale])
+            }
+            _ => Ok(batch),
+        }
+    }
+
+    /// The indexed map.
+    pub fn map(&self) -> &DBMap<K, V> {
+        &self.map
+    }
+
+    /// The index, mapping the index keys to the keys of the map.
+    pub fn index(&self) -> &DBMap<I, K> {
+        &self.index
+    }
+}
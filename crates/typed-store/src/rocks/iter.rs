@@ -12,18 +12,46 @@ use super::DBRawIteratorMultiThreaded;
 /// An iterator over all key-value pairs in a data map.
 pub struct Iter<'a, K, V> {
     db_iter: DBRawIteratorMultiThreaded<'a>,
+    stats: ScanStats,
     _phantom: PhantomData<(K, V)>,
 }
 
+/// The amount of data an iterator has scanned so far, in serialized form.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// the number of entries scanned
+    pub entries: u64,
+    /// the total size of the keys scanned, in bytes
+    pub key_bytes: u64,
+    /// the total size of the values scanned, in bytes
+    pub value_bytes: u64,
+}
+
+impl ScanStats {
+    fn record(&mut self, key: &[u8], value: &[u8]) {
+        self.entries += 1;
+        self.key_bytes += key.len() as u64;
+        self.value_bytes += value.len() as u64;
+    }
+}
+
 impl<'a, K: DeserializeOwned, V: DeserializeOwned> Iter<'a, K, V> {
     pub(super) fn new(db_iter: DBRawIteratorMultiThreaded<'a>) -> Self {
         Self {
             db_iter,
+            stats: ScanStats::default(),
             _phantom: PhantomData,
         }
     }
 }
 
+impl<'a, K, V> Iter<'a, K, V> {
+    /// The amount of data scanned by the iterator so far, which measures the I/O cost of a scan.
+    pub fn scan_stats(&self) -> ScanStats {
+        self.stats
+    }
+}
+
 impl<'a, K: DeserializeOwned, V: DeserializeOwned> Iterator for Iter<'a, K, V> {
     type Item = (K, V);
 
@@ -32,6 +60,9 @@ impl<'a, K: DeserializeOwned, V: DeserializeOwned> Iterator for Iter<'a, K, V> {
             let config = bincode::DefaultOptions::new()
                 .with_big_endian()
                 .with_fixint_encoding();
+            if let (Some(key), Some(value)) = (self.db_iter.key(), self.db_iter.value()) {
+                self.stats.record(key, value);
+            }
             let key = self.db_iter.key().and_then(|k| config.deserialize(k).ok());
             let value = self
                 .db_iter
@@ -69,3 +100,53 @@ impl<'a, K: Serialize, V> Iter<'a, K, V> {
         self
     }
 }
+
+/// An iterator over the key-value pairs in a data map, in descending key order.
+pub struct RevIter<'a, K, V> {
+    db_iter: DBRawIteratorMultiThreaded<'a>,
+    stats: ScanStats,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K: DeserializeOwned, V: DeserializeOwned> RevIter<'a, K, V> {
+    /// Expects an iterator that is already positioned on the greatest entry to yield.
+    pub(super) fn new(db_iter: DBRawIteratorMultiThreaded<'a>) -> Self {
+        Self {
+            db_iter,
+            stats: ScanStats::default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> RevIter<'a, K, V> {
+    /// The amount of data scanned by the iterator so far, which measures the I/O cost of a scan.
+    pub fn scan_stats(&self) -> ScanStats {
+        self.stats
+    }
+}
+
+impl<'a, K: DeserializeOwned, V: DeserializeOwned> Iterator for RevIter<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.db_iter.valid() {
+            let config = bincode::DefaultOptions::new()
+                .with_big_endian()
+                .with_fixint_encoding();
+            if let (Some(key), Some(value)) = (self.db_iter.key(), self.db_iter.value()) {
+                self.stats.record(key, value);
+            }
+            let key = self.db_iter.key().and_then(|k| config.deserialize(k).ok());
+            let value = self
+                .db_iter
+                .value()
+                .and_then(|v| bincode::deserialize(v).ok());
+
+            self.db_iter.prev();
+            key.and_then(|k| value.map(|v| (k, v)))
+        } else {
+            None
+        }
+    }
+}
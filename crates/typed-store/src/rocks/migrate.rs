@@ -0,0 +1,116 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{CancellationToken, DBMap, TypedStoreError};
+
+/// The number of entries written to the target of a migration in each batch.
+const MIGRATION_BATCH_SIZE: usize = 1000;
+
+/// The outcome of `DBMap::migrate_into`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// the number of entries written to the target
+    pub migrated: usize,
+    /// the number of entries the transform dropped
+    pub skipped: usize,
+    /// whether the migration was cancelled before reaching the end of the source
+    pub cancelled: bool,
+}
+
+impl<K, V> DBMap<K, V>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    /// Streams every entry of this map, in key order, through `transform` into `target`,
+    /// e.g. to rekey a table or reshape its values after a schema change. The entries
+    /// for which `transform` returns `None` are skipped, and this map is left untouched.
+    ///
+    /// The entries are written to the target in batches, which are each atomic: if the
+    /// migration fails midway, e.g. on an entry that doesn't deserialize, the batches
+    /// committed before the failure stay written.
+    pub fn migrate_into<NewK, NewV, F>(
+        &self,
+        target: &DBMap<NewK, NewV>,
+        transform: F,
+    ) -> Result<MigrationReport, TypedStoreError>
+    where
+        NewK: Serialize,
+        NewV: Serialize,
+        F: Fn(K, V) -> Option<(NewK, NewV)>,
+    {
+        self.migrate_into_with(target, transform, &CancellationToken::new(), None)
+    }
+
+    /// Same as `migrate_into`, for a migration running as a background task: it stops
+    /// before the next entry once `cancel` is cancelled, and `progress` is called after
+    /// every batch with the number of entries processed so far and the estimated number
+    /// of entries of this map.
+    ///
+    /// A cancelled migration is not an error: the entries processed before the
+    /// cancellation are written to the target, and the report is flagged `cancelled`.
+    pub fn migrate_into_with<NewK, NewV, F>(
+        &self,
+        target: &DBMap<NewK, NewV>,
+        transform: F,
+        cancel: &CancellationToken,
+        progress: Option<&dyn Fn(u64, u64)>,
+    ) -> Result<MigrationReport, TypedStoreError>
+    where
+        NewK: Serialize,
+        NewV: Serialize,
+        F: Fn(K, V) -> Option<(NewK, NewV)>,
+    {
+        let config = bincode::DefaultOptions::new()
+            .with_big_endian()
+            .with_fixint_encoding();
+        let total_estimate = self
+            .rocksdb
+            .property_int_value_cf(&self.cf(), "rocksdb.estimate-num-keys")?
+            .unwrap_or_default();
+        let mut report = MigrationReport::default();
+        let mut writer = target.batch_writer(MIGRATION_BATCH_SIZE);
+
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek_to_first();
+        while db_iter.valid() {
+            if cancel.is_cancelled() {
+                report.cancelled = true;
+                break;
+            }
+            if let (Some(key), Some(value)) = (db_iter.key(), db_iter.value()) {
+                let key = config.deserialize(key)?;
+                let value = bincode::deserialize(value)?;
+                match transform(key, value) {
+                    Some((new_key, new_value)) => {
+                        writer.insert(&new_key, &new_value)?;
+                        report.migrated += 1;
+                    }
+                    None => report.skipped += 1,
+                }
+            }
+            db_iter.next();
+
+            let processed = report.migrated + report.skipped;
+            if let Some(progress) = progress {
+                if processed % MIGRATION_BATCH_SIZE == 0 {
+                    progress(processed as u64, total_estimate);
+                }
+            }
+        }
+        db_iter.status()?;
+        writer.finish()?;
+
+        if let Some(progress) = progress {
+            let processed = report.migrated + report.skipped;
+            if processed % MIGRATION_BATCH_SIZE != 0 {
+                progress(processed as u64, total_estimate);
+            }
+        }
+        Ok(report)
+    }
+}
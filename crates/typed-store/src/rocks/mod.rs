@@ -1,9 +1,28 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+mod batch_writer;
+mod cancel;
+mod composite_key;
+mod counter;
+mod enum_key;
 mod errors;
+mod events;
+mod history;
+mod indexed;
 mod iter;
 mod keys;
+mod migrate;
+mod multi_map;
+mod options;
+mod perf;
+mod prefixed;
+mod secondary;
+mod snapshot;
+mod timestamp;
+mod ttl;
+mod validation;
 mod values;
+mod version;
 
 use crate::traits::Map;
 use bincode::Options;
@@ -13,8 +32,31 @@ use serde::{de::DeserializeOwned, Serialize};
 use std::{borrow::Borrow, marker::PhantomData, path::Path, sync::Arc};
 use tracing::instrument;
 
-use self::{iter::Iter, keys::Keys, values::Values};
+use self::{
+    iter::{Iter, RevIter},
+    keys::Keys,
+    values::Values,
+};
+pub use batch_writer::BatchWriter;
+pub use cancel::CancellationToken;
+pub use composite_key::{CompositeKey, CompositePrefix};
+pub use counter::Counter;
+pub use enum_key::{EnumKey, KeyDiscriminant};
 pub use errors::TypedStoreError;
+pub use events::{EventWatcher, TableEvent};
+pub use history::SequenceHistory;
+pub use indexed::IndexedDBMap;
+pub use iter::ScanStats;
+pub use migrate::MigrationReport;
+pub use multi_map::MultiMap;
+pub use options::DBMapOptions;
+pub use perf::PerfStats;
+pub use prefixed::{PrefixedDBMap, PrefixedIter};
+pub use secondary::SecondaryDBMap;
+pub use snapshot::MultiSnapshot;
+pub use timestamp::Timestamp;
+pub use ttl::TtlDBMap;
+pub use validation::ValidationReport;
 
 #[cfg(test)]
 mod tests;
@@ -22,6 +64,10 @@ mod tests;
 type DBRawIteratorMultiThreaded<'a> =
     rocksdb::DBRawIteratorWithThreadMode<'a, DBWithThreadMode<MultiThreaded>>;
 
+/// A page of a prefix scan, along with the cursor to the next page, see
+/// `DBMap::prefix_page`.
+pub type PrefixPage<K, V> = (Vec<(K, V)>, Option<K>);
+
 /// A helper macro to reopen multiple column families. The macro returns
 /// a tuple of DBMap structs in the same order that the column families
 /// are defined.
@@ -63,13 +109,43 @@ macro_rules! reopen {
     };
 }
 
+/// The name of the SST file written by `DBMap::export_snapshot`.
+const SNAPSHOT_FILE_NAME: &str = "snapshot.sst";
+
 /// An interface to a rocksDB database, keyed by a columnfamily
-#[derive(Clone, Debug)]
+///
+/// This is the low-level, synchronous API of the crate, for the callers that don't go
+/// through the async `Store`: the operations common to all the maps (`get`, `insert`,
+/// `remove`, the `multi_*` operations, `iter`...) are those of the `Map` trait, and the
+/// range and prefix scans (`range_iter`, `prefix_iter`...) are inherent methods. Each
+/// operation is performed on the calling thread, and blocks it on the database.
+///
+/// A `DBMap` is `Send` and `Sync`: RocksDB serves concurrent operations, so the map can
+/// be shared by threads, by reference or through its clones.
+///
+/// A `DBMap` is cheap to clone: the clones share the handle to the database, which is
+/// closed once the last of them is dropped. They are the way to hand the map to several
+/// tasks, as the database can't be opened twice.
+#[derive(Debug)]
 pub struct DBMap<K, V> {
     pub rocksdb: Arc<rocksdb::DBWithThreadMode<MultiThreaded>>,
     _phantom: PhantomData<fn(K) -> V>,
     // the rocksDB ColumnFamily under which the map is stored
     cf: String,
+    // whether the reads verify the checksums of the blocks they load
+    verify_checksums: bool,
+}
+
+// not derived, which would require the keys and values to be Clone
+impl<K, V> Clone for DBMap<K, V> {
+    fn clone(&self) -> Self {
+        DBMap {
+            rocksdb: self.rocksdb.clone(),
+            _phantom: PhantomData,
+            cf: self.cf.clone(),
+            verify_checksums: self.verify_checksums,
+        }
+    }
 }
 
 unsafe impl<K: Send, V: Send> Send for DBMap<K, V> {}
@@ -79,6 +155,10 @@ impl<K, V> DBMap<K, V> {
     ///
     /// This database is used to perform operations on single column family, and parametrizes
     /// all operations in `DBBatch` when writting across column families.
+    ///
+    /// The keys of the default column family starting with `typed_store/version/` are
+    /// reserved: they hold the versions of the tables of the other column families, for
+    /// the stores tracking them (see `StoreConfig::track_version`).
     #[instrument(level="debug", skip_all, fields(path = ?path.as_ref(), cf = ?opt_cf), err)]
     pub fn open<P: AsRef<Path>>(
         path: P,
@@ -93,9 +173,48 @@ impl<K, V> DBMap<K, V> {
             rocksdb,
             _phantom: PhantomData,
             cf: cf_key.to_string(),
+            verify_checksums: true,
         })
     }
 
+    /// Opens a database held entirely in memory, with specific options and an optional
+    /// column family. Unlike the in-memory `TestMap`, this is a real RocksDB instance, for
+    /// tests relying on its behavior (merge operators, compaction filters...) without
+    /// touching the disk.
+    ///
+    /// The data is lost when the database is closed, and every call opens a new, empty
+    /// database: the other column families must be attached with `reopen`.
+    #[instrument(level = "debug", skip(db_options), err)]
+    pub fn open_in_memory(
+        db_options: Option<rocksdb::Options>,
+        opt_cf: Option<&str>,
+    ) -> Result<Self, TypedStoreError> {
+        let mut options = db_options.unwrap_or_default();
+        options.set_env(&rocksdb::Env::mem_env()?);
+        // the path only names the database within its own memory environment
+        Self::open("/in-memory", Some(options), opt_cf)
+    }
+
+    /// Opens an existing database from a path, with new options for the map's column
+    /// family (and the default options for its other column families), so that the column
+    /// family can be retuned without recreating the database.
+    ///
+    /// The options taking effect on the open (e.g. the block cache or the memtable
+    /// sizes) apply right away. The options of the SST files (e.g. the compression) only
+    /// apply to the files written from then on, by flushes and compactions: the existing
+    /// files keep their settings, and stay readable, until they are compacted. The
+    /// comparator and the merge operator must stay compatible with the existing data.
+    #[instrument(level="debug", skip_all, fields(path = ?path.as_ref(), cf = ?opt_cf), err)]
+    pub fn reopen_with_options<P: AsRef<Path>>(
+        path: P,
+        opt_cf: Option<&str>,
+        cf_options: rocksdb::Options,
+    ) -> Result<Self, TypedStoreError> {
+        let cf_key = opt_cf.unwrap_or(rocksdb::DEFAULT_COLUMN_FAMILY_NAME);
+        let rocksdb = open_cf_opts(path, None, &[(cf_key, &cf_options)])?;
+        Self::reopen(&rocksdb, Some(cf_key))
+    }
+
     /// Reopens an open database as a typed map operating under a specific column family.
     /// if no column family is passed, the default column family is used.
     ///
@@ -124,13 +243,226 @@ impl<K, V> DBMap<K, V> {
             rocksdb: db.clone(),
             _phantom: PhantomData,
             cf: cf_key,
+            verify_checksums: true,
         })
     }
 
+    /// Returns a map over the same column family of the same database, whose values are
+    /// read and written as `NewV`, e.g. to write the values of a new schema while still
+    /// reading those of the old one during a migration.
+    ///
+    /// Nothing converts the stored bytes: the reads of either map decode whatever bytes
+    /// the other wrote, so this is only sound between value types with the same bincode
+    /// encoding, or for values known to have been written with the type they are read
+    /// with. Otherwise, the reads fail to deserialize, or worse, succeed with garbage.
+    pub fn reinterpret<NewV>(&self) -> DBMap<K, NewV> {
+        DBMap {
+            rocksdb: self.rocksdb.clone(),
+            _phantom: PhantomData,
+            cf: self.cf.clone(),
+            verify_checksums: self.verify_checksums,
+        }
+    }
+
+    /// Sets whether the reads of this map verify the checksums of the blocks they load
+    /// from the SST files, which RocksDB does by default: a corrupted block then fails the
+    /// read rather than returning garbage, at the cost of hashing every block loaded. This
+    /// applies to every read of the map, including those of the maps wrapping it (e.g. a
+    /// `TtlDBMap`) and of the snapshots; `verify_checksum` always verifies them.
+    pub fn with_verify_checksums(mut self, verify_checksums: bool) -> Self {
+        self.verify_checksums = verify_checksums;
+        self
+    }
+
+    /// The options of the reads of this map.
+    fn read_options(&self) -> rocksdb::ReadOptions {
+        let mut readopts = rocksdb::ReadOptions::default();
+        readopts.set_verify_checksums(self.verify_checksums);
+        readopts
+    }
+
+    /// The options of the reads of this map, restricted to the keys in `[start, end)`.
+    fn range_read_options(
+        &self,
+        start: &K,
+        end: &K,
+    ) -> Result<rocksdb::ReadOptions, TypedStoreError>
+    where
+        K: Serialize,
+    {
+        let mut readopts = self.read_options();
+        readopts.set_iterate_lower_bound(be_fix_int_ser(start)?);
+        readopts.set_iterate_upper_bound(be_fix_int_ser(end)?);
+        Ok(readopts)
+    }
+
+    /// The options of the reads of this map, restricted to the keys starting with
+    /// `prefix`, and if given, strictly greater than `after`.
+    fn prefix_read_options(&self, prefix: &[u8], after: Option<&[u8]>) -> rocksdb::ReadOptions {
+        let mut readopts = self.read_options();
+        let lower_bound = match after {
+            Some(after) if after >= prefix => {
+                // the smallest key strictly greater than `after`
+                let mut successor = after.to_vec();
+                successor.push(0);
+                successor
+            }
+            _ => prefix.to_vec(),
+        };
+        readopts.set_iterate_lower_bound(lower_bound);
+        if let Some(upper_bound) = prefix_upper_bound(prefix) {
+            readopts.set_iterate_upper_bound(upper_bound);
+        }
+        readopts
+    }
+
+    /// Drops the column family backing this map, deleting all of its data and
+    /// reclaiming its SST files. The map is consumed, so that it can't be used after
+    /// its table is gone.
+    #[instrument(level = "debug", skip_all, fields(cf = %self.cf), err)]
+    pub fn drop_cf(self) -> Result<(), TypedStoreError> {
+        drop_column_family(&self.rocksdb, &self.cf)
+    }
+
+    /// Flushes the memtables of this map's column family to SST files.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn flush(&self) -> Result<(), TypedStoreError> {
+        self.rocksdb.flush_cf(&self.cf())?;
+        Ok(())
+    }
+
+    /// Flushes the write-ahead log of the database from memory to the log file, and
+    /// if `sync` is set, fsyncs it. Writes made before the call, which are not synced
+    /// by default, are then durable without paying for a sync on every write.
+    #[instrument(level = "debug", skip(self), err)]
+    pub fn flush_wal(&self, sync: bool) -> Result<(), TypedStoreError> {
+        self.rocksdb.flush_wal(sync)?;
+        Ok(())
+    }
+
+    /// Exports the content of this map's column family to the directory `dir`, as SST
+    /// files that `import_snapshot` can ingest into another map, even one of a database
+    /// opened with different options. The export reflects a consistent view of the map.
+    #[instrument(level = "debug", skip_all, fields(dir = ?dir.as_ref()), err)]
+    pub fn export_snapshot<P: AsRef<Path>>(&self, dir: P) -> Result<(), TypedStoreError> {
+        std::fs::create_dir_all(&dir)?;
+        let options = rocksdb::Options::default();
+        let mut writer = rocksdb::SstFileWriter::create(&options);
+        let mut is_open = false;
+
+        // a raw iterator reads from an implicit snapshot of the database
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek_to_first();
+        while let (Some(key), Some(value)) = (db_iter.key(), db_iter.value()) {
+            if !is_open {
+                writer.open(dir.as_ref().join(SNAPSHOT_FILE_NAME))?;
+                is_open = true;
+            }
+            writer.put(key, value)?;
+            db_iter.next();
+        }
+        db_iter.status()?;
+
+        // an SST file can't be empty, an empty map exports no file at all
+        if is_open {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Ingests the SST files exported by `export_snapshot` in the directory `dir` into
+    /// this map's column family. Entries of the snapshot overwrite the existing ones.
+    #[instrument(level = "debug", skip_all, fields(dir = ?dir.as_ref()), err)]
+    pub fn import_snapshot<P: AsRef<Path>>(&self, dir: P) -> Result<(), TypedStoreError> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "sst") {
+                files.push(path);
+            }
+        }
+        if files.is_empty() {
+            return Ok(());
+        }
+        self.rocksdb.ingest_external_file_cf(&self.cf(), files)?;
+        Ok(())
+    }
+
+    /// Reports the estimated size of the live data of this map's column family next
+    /// to the total size of its SST files. The difference is (approximately) the space
+    /// taken by deleted or overwritten entries, that a compaction can reclaim.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn live_data_size(&self) -> Result<DataSize, TypedStoreError> {
+        let cf = self.cf();
+        let live = self
+            .rocksdb
+            .property_int_value_cf(&cf, "rocksdb.estimate-live-data-size")?
+            .unwrap_or_default();
+        let total = self
+            .rocksdb
+            .property_int_value_cf(&cf, "rocksdb.total-sst-files-size")?
+            .unwrap_or_default();
+        Ok(DataSize { live, total })
+    }
+
+    /// Reports, for each level of the LSM tree of this map's column family holding SST
+    /// files, in ascending level order, the number of files and their sizes, e.g. to
+    /// diagnose too many level-0 files or uneven levels. The figures are summed from the
+    /// metadata of the live files, which is exact where `rocksdb.levelstats` rounds the
+    /// sizes to megabytes.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn level_stats(&self) -> Result<Vec<LevelStat>, TypedStoreError> {
+        let mut levels = std::collections::BTreeMap::new();
+        for file in self.rocksdb.live_files()? {
+            if file.column_family_name != self.cf {
+                continue;
+            }
+            let stat = levels.entry(file.level).or_insert(LevelStat {
+                level: file.level,
+                ..LevelStat::default()
+            });
+            stat.files += 1;
+            stat.bytes += file.size as u64;
+            stat.entries += file.num_entries;
+            stat.deletions += file.num_deletions;
+        }
+        Ok(levels.into_values().collect())
+    }
+
+    /// Reports the approximate size in bytes of the active memtable of this map's column
+    /// family, i.e. of the writes that a flush would persist to a new SST file.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn memtable_size(&self) -> Result<u64, TypedStoreError> {
+        let size = self
+            .rocksdb
+            .property_int_value_cf(&self.cf(), "rocksdb.cur-size-active-mem-table")?
+            .unwrap_or_default();
+        Ok(size)
+    }
+
+    /// Returns the dump of the statistics RocksDB collected over the database, or `None`
+    /// if their collection is not enabled, see `DBMapOptions::enable_statistics`.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn statistics_string(&self) -> Result<Option<String>, TypedStoreError> {
+        Ok(self.rocksdb.property_value("rocksdb.options-statistics")?)
+    }
+
     pub fn batch(&self) -> DBBatch {
         DBBatch::new(&self.rocksdb)
     }
 
+    /// Returns a writer streaming writes into this map, committed in batches of
+    /// `batch_size` operations.
+    pub fn batch_writer(&self, batch_size: usize) -> BatchWriter<'_, K, V>
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        BatchWriter::new(self, batch_size)
+    }
+
     fn cf(&self) -> Arc<rocksdb::BoundColumnFamily<'_>> {
         self.rocksdb
             .cf_handle(&self.cf)
@@ -138,6 +470,497 @@ impl<K, V> DBMap<K, V> {
     }
 }
 
+impl<K: Serialize, V> DBMap<K, V> {
+    /// Reads the given keys without deserializing their values, to load the blocks they
+    /// are stored in into the block cache, so that later reads of these keys (and of the
+    /// keys stored next to them) hit a warm cache, e.g. after a restart.
+    ///
+    /// This blocks on disk reads, and is best run on a blocking thread.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn warmup(&self, keys: &[K]) -> Result<(), TypedStoreError> {
+        let cf = self.cf();
+        let mut readopts = self.read_options();
+        readopts.fill_cache(true);
+        for key in keys {
+            let key_buf = be_fix_int_ser(key)?;
+            self.rocksdb.get_pinned_cf_opt(&cf, &key_buf, &readopts)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts key-value pairs, applying the policy to the keys that already hold a
+    /// value, and returns how many pairs were written. The pairs written are written
+    /// atomically, in a single batch.
+    ///
+    /// The existing keys are looked up before the batch is written, so a concurrent
+    /// insertion of the same keys can go unnoticed.
+    #[instrument(level = "trace", skip_all, err)]
+    pub fn multi_insert_with_policy<J, U>(
+        &self,
+        key_val_pairs: impl IntoIterator<Item = (J, U)>,
+        policy: InsertPolicy,
+    ) -> Result<usize, TypedStoreError>
+    where
+        J: Borrow<K>,
+        U: Borrow<V>,
+        V: Serialize,
+    {
+        let mut key_val_pairs: Vec<_> = key_val_pairs.into_iter().collect();
+        if policy != InsertPolicy::Overwrite {
+            let keys = key_val_pairs.iter().map(|(k, _)| Borrow::<K>::borrow(k));
+            let found = self.multi_contains_key(keys)?;
+            if policy == InsertPolicy::FailIfExists && found.contains(&true) {
+                return Err(TypedStoreError::KeyExists);
+            }
+            let mut found = found.into_iter();
+            key_val_pairs.retain(|_| !found.next().unwrap_or_default());
+        }
+        let written = key_val_pairs.len();
+        DBBatch::new(&self.rocksdb)
+            .insert_batch(self, key_val_pairs)?
+            .write()?;
+        Ok(written)
+    }
+
+    /// Returns the smallest key of the map, if any. Unlike the `Keys` iterator, which
+    /// ends on a key that fails to deserialize, this reports the failure.
+    pub fn first_key(&self) -> Result<Option<K>, TypedStoreError>
+    where
+        K: DeserializeOwned,
+    {
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek_to_first();
+        decode_key(&db_iter)
+    }
+
+    /// Returns the greatest key of the map, if any, reporting a failure to deserialize it
+    /// like `first_key`.
+    pub fn last_key(&self) -> Result<Option<K>, TypedStoreError>
+    where
+        K: DeserializeOwned,
+    {
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek_to_last();
+        decode_key(&db_iter)
+    }
+
+    /// Checks that the serialized keys of the map sort in the same order as the keys
+    /// themselves, on the given sample of keys along with the first and last keys stored,
+    /// so that range and prefix scans return the keys in order. This is not the case of
+    /// e.g. negative integers, which serialize after the positive ones, or of strings,
+    /// which sort by length first.
+    ///
+    /// Fails with `KeyOrdering`, naming the first two keys out of order, or with the
+    /// error of decoding the first or last key stored.
+    pub fn validate_key_ordering(&self, probes: &[K]) -> Result<(), TypedStoreError>
+    where
+        K: DeserializeOwned + Ord + std::fmt::Debug,
+    {
+        let stored = [self.first_key()?, self.last_key()?];
+        let mut probes: Vec<_> = probes.iter().chain(stored.iter().flatten()).collect();
+        probes.sort();
+        probes.dedup();
+        let serialized = probes
+            .iter()
+            .map(|key| be_fix_int_ser(*key))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, pair) in serialized.windows(2).enumerate() {
+            if pair[0] >= pair[1] {
+                return Err(TypedStoreError::KeyOrdering(format!(
+                    "{:?} is stored after {:?}",
+                    probes[i],
+                    probes[i + 1]
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns, for each of the keys provided, whether the map contains a value for it.
+    /// Values are never deserialized, and the keys that the bloom filters rule out are
+    /// not looked up at all.
+    #[instrument(level = "trace", skip_all, err)]
+    pub fn multi_contains_key<J>(
+        &self,
+        keys: impl IntoIterator<Item = J>,
+    ) -> Result<Vec<bool>, TypedStoreError>
+    where
+        J: Borrow<K>,
+    {
+        let cf = self.cf();
+        let mut contained = Vec::new();
+        let mut candidates = Vec::new();
+        for key in keys {
+            let key_buf = be_fix_int_ser(key.borrow())?;
+            // `key_may_exist_cf` can have false positives, which the lookup below confirms
+            if self.rocksdb.key_may_exist_cf(&cf, &key_buf) {
+                candidates.push((contained.len(), key_buf));
+            }
+            contained.push(false);
+        }
+
+        let lookups = candidates.iter().map(|(_, key_buf)| (&cf, key_buf));
+        let results = self.rocksdb.multi_get_cf_opt(lookups, &self.read_options());
+        for ((position, _), result) in candidates.iter().zip(results) {
+            contained[*position] = result?.is_some();
+        }
+        Ok(contained)
+    }
+
+    /// Returns the raw, still serialized, bytes of the value for the given key, pinned
+    /// in RocksDB's block cache or memtable rather than copied out. A projection of the
+    /// value (e.g. a header it starts with) can then be decoded without allocating and
+    /// deserializing the whole value.
+    ///
+    /// The slice borrows from the map, and keeps the underlying memory pinned for as long
+    /// as it lives: it should be dropped promptly rather than held on to.
+    pub fn get_raw_pinned(
+        &self,
+        key: &K,
+    ) -> Result<Option<rocksdb::DBPinnableSlice<'_>>, TypedStoreError> {
+        let key_buf = be_fix_int_ser(key)?;
+        let res = self
+            .rocksdb
+            .get_pinned_cf_opt(&self.cf(), &key_buf, &self.read_options())?;
+        Ok(res)
+    }
+}
+
+/// How `DBMap::multi_insert_with_policy` treats the keys that already hold a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertPolicy {
+    /// the existing values are overwritten, as with `multi_insert`
+    Overwrite,
+    /// the pairs of the existing keys are left out, and the others inserted
+    SkipExisting,
+    /// nothing is inserted if any key exists, which fails with `KeyExists`
+    FailIfExists,
+}
+
+/// The on-disk size of a column family, as reported by `DBMap::live_data_size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataSize {
+    /// the estimated size of the live data, in bytes
+    pub live: u64,
+    /// the total size of the SST files, in bytes
+    pub total: u64,
+}
+
+/// The SST files of a level of a column family, as reported by `DBMap::level_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelStat {
+    pub level: i32,
+    /// the number of SST files in the level
+    pub files: usize,
+    /// the total size of the files, in bytes
+    pub bytes: u64,
+    /// the number of entries in the files, including the overwritten ones
+    pub entries: u64,
+    /// the number of tombstones in the files
+    pub deletions: u64,
+}
+
+impl DataSize {
+    /// The space, in bytes, that a compaction is expected to reclaim.
+    pub fn reclaimable(&self) -> u64 {
+        self.total.saturating_sub(self.live)
+    }
+}
+
+impl<K, V> DBMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: DeserializeOwned,
+{
+    /// Returns an iterator over the entries with keys in `[start, end)`, in ascending key order.
+    pub fn range_iter(&self, start: &K, end: &K) -> Result<Iter<'_, K, V>, TypedStoreError> {
+        let readopts = self.range_read_options(start, end)?;
+        let mut db_iter = self.rocksdb.raw_iterator_cf_opt(&self.cf(), readopts);
+        db_iter.seek_to_first();
+
+        Ok(Iter::new(db_iter))
+    }
+
+    /// Returns an iterator over the entries with keys in `[start, end)`, in descending key order.
+    ///
+    /// The bounds are enforced by RocksDB (`iterate_lower_bound` / `iterate_upper_bound`)
+    /// rather than by seeking: the iterator starts at the greatest key strictly smaller
+    /// than `end`, and stops before going below `start`.
+    pub fn range_iter_rev(&self, start: &K, end: &K) -> Result<RevIter<'_, K, V>, TypedStoreError> {
+        let readopts = self.range_read_options(start, end)?;
+        let mut db_iter = self.rocksdb.raw_iterator_cf_opt(&self.cf(), readopts);
+        db_iter.seek_to_last();
+
+        Ok(RevIter::new(db_iter))
+    }
+
+    /// Returns the entry with the greatest key smaller than or equal to `key`, if any.
+    pub fn floor(&self, key: &K) -> Result<Option<(K, V)>, TypedStoreError> {
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek_for_prev(be_fix_int_ser(key)?);
+        decode_entry(&db_iter)
+    }
+
+    /// Returns the entry with the smallest key greater than or equal to `key`, if any.
+    pub fn ceil(&self, key: &K) -> Result<Option<(K, V)>, TypedStoreError> {
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek(be_fix_int_ser(key)?);
+        decode_entry(&db_iter)
+    }
+
+    /// Returns an iterator over the entries whose serialized key starts with the
+    /// serialized `prefix`, in ascending key order. Since keys are serialized field by
+    /// field, the prefix of a key `(a, b)` is typically its first field `a`.
+    pub fn prefix_iter<P: Serialize + ?Sized>(
+        &self,
+        prefix: &P,
+    ) -> Result<Iter<'_, K, V>, TypedStoreError> {
+        let prefix = be_fix_int_ser(prefix)?;
+        let readopts = self.prefix_read_options(&prefix, None);
+        let mut db_iter = self.rocksdb.raw_iterator_cf_opt(&self.cf(), readopts);
+        db_iter.seek_to_first();
+
+        Ok(Iter::new(db_iter))
+    }
+
+    /// Counts the entries whose serialized key starts with the serialized `prefix`,
+    /// without reading or deserializing their keys and values.
+    #[instrument(level = "trace", skip_all, err)]
+    pub fn prefix_count<P: Serialize + ?Sized>(
+        &self,
+        prefix: &P,
+    ) -> Result<usize, TypedStoreError> {
+        let prefix = be_fix_int_ser(prefix)?;
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.prefix_read_options(&prefix, None));
+        db_iter.seek_to_first();
+
+        let mut count = 0;
+        while db_iter.valid() {
+            count += 1;
+            db_iter.next();
+        }
+        db_iter.status()?;
+        Ok(count)
+    }
+
+    /// Deletes the entries whose serialized key starts with the serialized `prefix`, with
+    /// a single range deletion, and returns the number of entries deleted. The count is
+    /// only exact if the prefix is not written to concurrently.
+    #[instrument(level = "trace", skip_all, err)]
+    pub fn delete_prefix<P: Serialize + ?Sized>(&self, prefix: &P) -> Result<u64, TypedStoreError> {
+        self.delete_prefix_raw(&be_fix_int_ser(prefix)?)
+    }
+
+    pub(crate) fn delete_prefix_raw(&self, prefix: &[u8]) -> Result<u64, TypedStoreError> {
+        let (batch, count) = self.delete_prefix_batch(self.batch(), prefix)?;
+        batch.write()?;
+        Ok(count)
+    }
+
+    /// Adds the deletion of the keys whose serialized form starts with `prefix` to the
+    /// batch, returned along with the number of keys it deletes.
+    pub(crate) fn delete_prefix_batch(
+        &self,
+        mut batch: DBBatch,
+        prefix: &[u8],
+    ) -> Result<(DBBatch, u64), TypedStoreError> {
+        if !Arc::ptr_eq(&self.rocksdb, &batch.rocksdb) {
+            return Err(TypedStoreError::CrossDBBatch);
+        }
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.prefix_read_options(prefix, None));
+        db_iter.seek_to_first();
+
+        let mut count = 0;
+        match prefix_upper_bound(prefix) {
+            Some(upper_bound) => {
+                while db_iter.valid() {
+                    count += 1;
+                    db_iter.next();
+                }
+                batch
+                    .batch
+                    .delete_range_cf(&self.cf(), prefix, &upper_bound[..]);
+            }
+            // the prefix is empty or all 0xFF: the keys can't be bounded, and are
+            // deleted one by one
+            None => {
+                while let Some(key) = db_iter.key() {
+                    batch.batch.delete_cf(&self.cf(), key);
+                    count += 1;
+                    db_iter.next();
+                }
+            }
+        }
+        db_iter.status()?;
+        Ok((batch, count))
+    }
+
+    /// Returns a page of at most `limit` entries whose serialized key starts with the
+    /// serialized `prefix`, and which come strictly after the key `after` (or from the
+    /// start of the prefix if `after` is `None`), in ascending key order.
+    ///
+    /// Along with the page comes the cursor to pass as `after` to fetch the next page:
+    /// the last key of the page, or `None` once the prefix is exhausted. As the cursor is
+    /// a key rather than an offset, keys inserted in between calls never make a page
+    /// repeat or skip entries.
+    ///
+    /// A `limit` of 0 fails with `InvalidScanBounds`: its pages would never make progress.
+    pub fn prefix_page<P: Serialize + ?Sized>(
+        &self,
+        prefix: &P,
+        after: Option<&K>,
+        limit: usize,
+    ) -> Result<PrefixPage<K, V>, TypedStoreError> {
+        let after = after.map(be_fix_int_ser).transpose()?;
+        self.prefix_page_raw(&be_fix_int_ser(prefix)?, after, limit)
+    }
+
+    /// `prefix_page` over an already serialized prefix and cursor.
+    pub(crate) fn prefix_page_raw(
+        &self,
+        prefix: &[u8],
+        after: Option<Vec<u8>>,
+        limit: usize,
+    ) -> Result<PrefixPage<K, V>, TypedStoreError> {
+        if limit == 0 {
+            return Err(TypedStoreError::InvalidScanBounds);
+        }
+        let config = bincode::DefaultOptions::new()
+            .with_big_endian()
+            .with_fixint_encoding();
+        let mut db_iter = self.rocksdb.raw_iterator_cf_opt(
+            &self.cf(),
+            self.prefix_read_options(prefix, after.as_deref()),
+        );
+        db_iter.seek_to_first();
+
+        let mut page = Vec::new();
+        let mut last_key = after;
+        while page.len() < limit {
+            match (db_iter.key(), db_iter.value()) {
+                (Some(key), Some(value)) => {
+                    page.push((config.deserialize(key)?, bincode::deserialize(value)?));
+                    last_key = Some(key.to_vec());
+                }
+                _ => break,
+            }
+            db_iter.next();
+        }
+        db_iter.status()?;
+
+        let cursor = match last_key {
+            Some(key) if db_iter.valid() => Some(config.deserialize(&key)?),
+            _ => None,
+        };
+        Ok((page, cursor))
+    }
+
+    /// Returns at most `limit` entries with serialized keys in `[start, end)`, where a
+    /// missing bound leaves the range open, in ascending key order or descending if
+    /// `reverse` is set. A `readahead_size` sets the prefetching of the SST files.
+    pub(crate) fn scan_raw(
+        &self,
+        start: Option<Vec<u8>>,
+        end: Option<Vec<u8>>,
+        limit: Option<usize>,
+        reverse: bool,
+        readahead_size: Option<usize>,
+    ) -> Result<Vec<(K, V)>, TypedStoreError> {
+        let config = bincode::DefaultOptions::new()
+            .with_big_endian()
+            .with_fixint_encoding();
+        let mut readopts = self.read_options();
+        if let Some(start) = start {
+            readopts.set_iterate_lower_bound(start);
+        }
+        if let Some(end) = end {
+            readopts.set_iterate_upper_bound(end);
+        }
+        if let Some(readahead_size) = readahead_size {
+            readopts.set_readahead_size(readahead_size);
+        }
+        let mut db_iter = self.rocksdb.raw_iterator_cf_opt(&self.cf(), readopts);
+        if reverse {
+            db_iter.seek_to_last();
+        } else {
+            db_iter.seek_to_first();
+        }
+
+        let mut entries = Vec::new();
+        while entries.len() < limit.unwrap_or(usize::MAX) {
+            match (db_iter.key(), db_iter.value()) {
+                (Some(key), Some(value)) => {
+                    entries.push((config.deserialize(key)?, bincode::deserialize(value)?))
+                }
+                _ => break,
+            }
+            if reverse {
+                db_iter.prev();
+            } else {
+                db_iter.next();
+            }
+        }
+        db_iter.status()?;
+        Ok(entries)
+    }
+}
+
+/// Decodes the entry a raw iterator is positioned on, if it is positioned on one.
+fn decode_entry<K: DeserializeOwned, V: DeserializeOwned>(
+    db_iter: &DBRawIteratorMultiThreaded<'_>,
+) -> Result<Option<(K, V)>, TypedStoreError> {
+    db_iter.status()?;
+    match (db_iter.key(), db_iter.value()) {
+        (Some(key), Some(value)) => {
+            let config = bincode::DefaultOptions::new()
+                .with_big_endian()
+                .with_fixint_encoding();
+            Ok(Some((
+                config.deserialize(key)?,
+                bincode::deserialize(value)?,
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn decode_key<K: DeserializeOwned>(
+    db_iter: &DBRawIteratorMultiThreaded<'_>,
+) -> Result<Option<K>, TypedStoreError> {
+    db_iter.status()?;
+    let config = bincode::DefaultOptions::new()
+        .with_big_endian()
+        .with_fixint_encoding();
+    Ok(db_iter
+        .key()
+        .map(|key| config.deserialize(key))
+        .transpose()?)
+}
+
+/// The smallest key greater than all the keys starting with `prefix`, if there is one.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut upper_bound = prefix.to_vec();
+    while let Some(last) = upper_bound.pop() {
+        if last < u8::MAX {
+            upper_bound.push(last + 1);
+            return Some(upper_bound);
+        }
+    }
+    None
+}
+
 /// Provides a mutable struct to form a collection of database write operations, and execute them.
 ///
 /// Batching write and delete operations is faster than performing them one by one and ensures their atomicity,
@@ -282,13 +1105,18 @@ where
         // [`rocksdb::DBWithThreadMode::key_may_exist_cf`] can have false positives,
         // but no false negatives. We use it to short-circuit the absent case
         Ok(self.rocksdb.key_may_exist_cf(&self.cf(), &key_buf)
-            && self.rocksdb.get_pinned_cf(&self.cf(), &key_buf)?.is_some())
+            && self
+                .rocksdb
+                .get_pinned_cf_opt(&self.cf(), &key_buf, &self.read_options())?
+                .is_some())
     }
 
     #[instrument(level = "trace", skip_all, err)]
     fn get(&self, key: &K) -> Result<Option<V>, TypedStoreError> {
         let key_buf = be_fix_int_ser(key)?;
-        let res = self.rocksdb.get_pinned_cf(&self.cf(), &key_buf)?;
+        let res = self
+            .rocksdb
+            .get_pinned_cf_opt(&self.cf(), &key_buf, &self.read_options())?;
         match res {
             Some(data) => Ok(Some(bincode::deserialize(&data)?)),
             None => Ok(None),
@@ -325,21 +1153,27 @@ where
     }
 
     fn iter(&'a self) -> Self::Iterator {
-        let mut db_iter = self.rocksdb.raw_iterator_cf(&self.cf());
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
         db_iter.seek_to_first();
 
         Iter::new(db_iter)
     }
 
     fn keys(&'a self) -> Self::Keys {
-        let mut db_iter = self.rocksdb.raw_iterator_cf(&self.cf());
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
         db_iter.seek_to_first();
 
         Keys::new(db_iter)
     }
 
     fn values(&'a self) -> Self::Values {
-        let mut db_iter = self.rocksdb.raw_iterator_cf(&self.cf());
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
         db_iter.seek_to_first();
 
         Values::new(db_iter)
@@ -361,7 +1195,9 @@ where
             .map(|k| Ok((&cf, be_fix_int_ser(k.borrow())?)))
             .collect();
 
-        let results = self.rocksdb.multi_get_cf(keys_bytes?);
+        let results = self
+            .rocksdb
+            .multi_get_cf_opt(keys_bytes?, &self.read_options());
 
         let values_parsed: Result<Vec<_>, TypedStoreError> = results
             .into_iter()
@@ -474,6 +1310,65 @@ pub fn open_cf_opts<P: AsRef<Path>>(
     Ok(rocksdb)
 }
 
+/// Creates an environment sized with the given number of low priority (compaction) and
+/// high priority (flush) background threads.
+///
+/// Databases that should share these thread pools (and any I/O priority set on the
+/// returned `Env`) are each opened with options on which the env is installed through
+/// `rocksdb::Options::set_env`, this bounds the background threads of many tables
+/// regardless of how many databases they are spread over.
+pub fn shared_env(
+    background_threads: i32,
+    high_priority_background_threads: i32,
+) -> Result<rocksdb::Env, TypedStoreError> {
+    let mut env = rocksdb::Env::default()?;
+    env.set_background_threads(background_threads);
+    env.set_high_priority_background_threads(high_priority_background_threads);
+    Ok(env)
+}
+
+/// Reports the total size on disk of the database: the SST files of all its column
+/// families, along with its write-ahead log files.
+#[instrument(level = "debug", skip(db), err)]
+pub fn total_size_on_disk(
+    db: &Arc<rocksdb::DBWithThreadMode<MultiThreaded>>,
+) -> Result<u64, TypedStoreError> {
+    let mut total = 0;
+    let cfs = rocksdb::DBWithThreadMode::<MultiThreaded>::list_cf(
+        &rocksdb::Options::default(),
+        db.path(),
+    )?;
+    for cf_name in cfs {
+        if let Some(cf) = db.cf_handle(&cf_name) {
+            total += db
+                .property_int_value_cf(&cf, "rocksdb.total-sst-files-size")?
+                .unwrap_or_default();
+        }
+    }
+
+    // the write-ahead log lives next to the SST files, in `.log` files
+    for entry in std::fs::read_dir(db.path())? {
+        let entry = entry?;
+        if entry.path().extension().map_or(false, |ext| ext == "log") {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Drops a column family from the database, deleting all of its data.
+///
+/// Any `DBMap` still opened on this column family panics on its next operation,
+/// prefer `DBMap::drop_cf` which consumes the map.
+#[instrument(level = "debug", skip(db), err)]
+pub fn drop_column_family(
+    db: &Arc<rocksdb::DBWithThreadMode<MultiThreaded>>,
+    name: &str,
+) -> Result<(), TypedStoreError> {
+    db.drop_cf(name)?;
+    Ok(())
+}
+
 /// TODO: Good description of why we're doing this : RocksDB stores keys in BE and has a seek operator on iterators, see https://github.com/facebook/rocksdb/wiki/Iterator#introduction
 #[inline]
 pub(crate) fn be_fix_int_ser<S>(t: &S) -> Result<Vec<u8>, TypedStoreError>
@@ -0,0 +1,62 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, CompositeKey, DBMap, TypedStoreError};
+
+/// A map holding a set of values per key, e.g. the tags of an item or the neighbours
+/// of a node.
+///
+/// Each `(key, value)` pair is stored as a `CompositeKey` with an empty value, so the
+/// values of a key are kept sorted (by their serialized form), and are enumerated with
+/// a prefix scan of the key.
+pub struct MultiMap<K, V> {
+    map: DBMap<CompositeKey<K, V>, ()>,
+}
+
+impl<K, V> MultiMap<K, V> {
+    pub fn new(map: DBMap<CompositeKey<K, V>, ()>) -> Self {
+        MultiMap { map }
+    }
+}
+
+impl<K, V> MultiMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Adds the value to the set of the key. Adding a value twice has no effect.
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), TypedStoreError> {
+        // (&K, &V) serializes exactly as the stored CompositeKey<K, V>
+        let key_buf = be_fix_int_ser(&(key, value))?;
+        self.map.rocksdb.put_cf(&self.map.cf(), &key_buf, b"")?;
+        Ok(())
+    }
+
+    /// Removes the value from the set of the key.
+    pub fn remove(&self, key: &K, value: &V) -> Result<(), TypedStoreError> {
+        let key_buf = be_fix_int_ser(&(key, value))?;
+        self.map.rocksdb.delete_cf(&self.map.cf(), &key_buf)?;
+        Ok(())
+    }
+
+    /// Returns true if the value is in the set of the key.
+    pub fn contains(&self, key: &K, value: &V) -> Result<bool, TypedStoreError> {
+        let key_buf = be_fix_int_ser(&(key, value))?;
+        Ok(self
+            .map
+            .rocksdb
+            .get_pinned_cf_opt(&self.map.cf(), &key_buf, &self.map.read_options())?
+            .is_some())
+    }
+
+    /// Returns the set of values of the key, in the order of their serialized form.
+    pub fn get_all(&self, key: &K) -> Result<Vec<V>, TypedStoreError> {
+        let values = self
+            .map
+            .prefix_iter(key)?
+            .map(|(composite_key, ())| composite_key.second)
+            .collect();
+        Ok(values)
+    }
+}
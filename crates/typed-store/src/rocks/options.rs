@@ -0,0 +1,178 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use rocksdb::{compaction_filter::Decision, MergeOperands};
+
+/// Options for opening the database behind a `DBMap`, as a builder over `rocksdb::Options`.
+///
+/// The options convert into `rocksdb::Options`, to be passed to `DBMap::open`,
+/// `open_cf` or `open_cf_opts`:
+/// ```
+/// # use typed_store::rocks::*;
+/// # use tempfile::tempdir;
+/// // entries with an empty value are dropped on compaction
+/// let options = DBMapOptions::new().with_compaction_filter("empty", |_key, value| value.is_empty());
+/// let db = DBMap::<u32, Vec<u8>>::open(tempdir().unwrap(), Some(options.into()), None).unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct DBMapOptions {
+    options: rocksdb::Options,
+}
+
+impl DBMapOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of files the database keeps open, which bounds its use of
+    /// file descriptors, at the cost of re-opening SST files on reads. `-1` (the default)
+    /// keeps all the files open, for the lowest read latency. RocksDB raises values lower
+    /// than 20 to 20.
+    pub fn max_open_files(mut self, max_open_files: i32) -> Self {
+        self.options.set_max_open_files(max_open_files);
+        self
+    }
+
+    /// Sets the number of level-0 files that triggers a compaction into level 1 (4 by
+    /// default). Raising it batches more flushed files per compaction.
+    pub fn level0_file_num_compaction_trigger(mut self, files: i32) -> Self {
+        self.options
+            .set_level_zero_file_num_compaction_trigger(files);
+        self
+    }
+
+    /// Sets the number of level-0 files from which writes are slowed down (20 by
+    /// default), until compactions catch up.
+    pub fn level0_slowdown_writes_trigger(mut self, files: i32) -> Self {
+        self.options.set_level_zero_slowdown_writes_trigger(files);
+        self
+    }
+
+    /// Sets the number of level-0 files from which writes are stopped (36 by default),
+    /// until compactions catch up. Raising the two write triggers lets bursts of writes
+    /// pile up level-0 files without stalling, at the cost of slower reads meanwhile.
+    pub fn level0_stop_writes_trigger(mut self, files: i32) -> Self {
+        self.options.set_level_zero_stop_writes_trigger(files);
+        self
+    }
+
+    /// Collects the statistics of RocksDB (tickers, e.g. the block cache hits, and
+    /// histograms, e.g. the read latencies), as reported by `DBMap::statistics_string`.
+    /// The collection slows every operation down slightly.
+    pub fn enable_statistics(mut self) -> Self {
+        self.options.enable_statistics();
+        self
+    }
+
+    /// Sets the compression of the bottommost level, which holds the largest and coldest
+    /// part of the data: a heavier compression there (e.g. zstd) saves space without
+    /// slowing down the flushes and the compactions of the upper levels.
+    pub fn bottommost_compression(mut self, compression: rocksdb::DBCompressionType) -> Self {
+        self.options.set_bottommost_compression_type(compression);
+        self
+    }
+
+    /// Allocates a bloom filter of `ratio` times the memtable size over the key prefixes
+    /// of the memtable (see `with_hash_memtable` for the prefix extractor), so that the
+    /// lookups of keys absent from the memtable skip searching it.
+    pub fn memtable_prefix_bloom_ratio(mut self, ratio: f64) -> Self {
+        self.options.set_memtable_prefix_bloom_ratio(ratio);
+        self
+    }
+
+    /// Adds the whole keys to the memtable bloom filter, rather than only their prefixes,
+    /// which makes it effective for point lookups. The filter is only allocated with a
+    /// non-zero `memtable_prefix_bloom_ratio`.
+    pub fn memtable_whole_key_filtering(mut self, whole_key_filtering: bool) -> Self {
+        self.options
+            .set_memtable_whole_key_filtering(whole_key_filtering);
+        self
+    }
+
+    /// Lets the writes to the database become visible out of order, in exchange for a
+    /// higher write throughput with concurrent writers: a write no longer waits for the
+    /// writes sequenced before it to reach the memtable.
+    ///
+    /// A reader may then observe a write without the concurrent writes sequenced before
+    /// it, and a snapshot may miss writes older than its sequence number, until they
+    /// land. The writes of a single thread, and each batch, are unaffected. This is only
+    /// sound when the concurrent writers are independent, and is a database wide option,
+    /// to pass when opening the database; it is off by default.
+    pub fn unordered_write(mut self, unordered_write: bool) -> Self {
+        self.options.set_unordered_write(unordered_write);
+        self
+    }
+
+    /// Flushes the memtables of all the column families of the database together,
+    /// atomically: after a crash, the column families written by the same cross-column
+    /// family `DBBatch` are then never recovered at different points. This is a database
+    /// wide option, to pass when opening the database (e.g. with `open_cf`).
+    pub fn atomic_flush(mut self, atomic_flush: bool) -> Self {
+        self.options.set_atomic_flush(atomic_flush);
+        self
+    }
+
+    /// Uses a hash-based memtable, indexed by the first `prefix_len` bytes of the
+    /// serialized keys, for column families that only serve point lookups: gets and
+    /// inserts of recent entries are faster than with the default skiplist memtable.
+    ///
+    /// Range scans are unsupported in this mode, iterators (`iter`, `range_iter`,
+    /// `keys`...) may skip entries or return them out of order. All the keys should be
+    /// at least `prefix_len` bytes long once serialized, which the serialized size of a
+    /// fixed-size key type (e.g. 8 for a `u64`) guarantees.
+    pub fn with_hash_memtable(mut self, prefix_len: usize, bucket_count: usize) -> Self {
+        self.options
+            .set_prefix_extractor(rocksdb::SliceTransform::create_fixed_prefix(prefix_len));
+        self.options
+            .set_memtable_factory(rocksdb::MemtableFactory::HashLinkList { bucket_count });
+        // hash memtables don't support concurrent writes
+        self.options.set_allow_concurrent_memtable_write(false);
+        self
+    }
+
+    /// Registers an associative merge operator: `merge` is called with the key, its
+    /// existing value if any, and the operands merged into the key since, and returns the
+    /// merged value (or `None` to fail the merge). Merges are written with `merge_cf`.
+    pub fn with_merge_operator<F>(mut self, name: &str, merge: F) -> Self
+    where
+        F: Fn(&[u8], Option<&[u8]>, &MergeOperands) -> Option<Vec<u8>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.options.set_merge_operator_associative(name, merge);
+        self
+    }
+
+    /// Registers a compaction filter, dropping during compactions the entries for which
+    /// `filter` returns true when called with their raw key and value bytes. This
+    /// garbage-collects entries without explicit deletes, whenever their data is compacted.
+    ///
+    /// The filter is called concurrently from the compaction threads, and must be cheap.
+    pub fn with_compaction_filter<F>(mut self, name: &str, filter: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> bool + Send + 'static,
+    {
+        self.options
+            .set_compaction_filter(name, move |_level: u32, key: &[u8], value: &[u8]| {
+                if filter(key, value) {
+                    Decision::Remove
+                } else {
+                    Decision::Keep
+                }
+            });
+        self
+    }
+}
+
+impl From<rocksdb::Options> for DBMapOptions {
+    fn from(options: rocksdb::Options) -> Self {
+        DBMapOptions { options }
+    }
+}
+
+impl From<DBMapOptions> for rocksdb::Options {
+    fn from(options: DBMapOptions) -> Self {
+        options.options
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use rocksdb::perf::{set_perf_stats, PerfContext, PerfMetric, PerfStatsLevel};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{DBMap, TypedStoreError};
+use crate::traits::Map;
+
+/// The work RocksDB performed to serve an operation, as collected by its perf context.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerfStats {
+    /// the number of blocks read from the SST files (i.e. missing the block cache)
+    pub block_read_count: u64,
+    /// the number of bytes of the blocks read from the SST files
+    pub block_read_bytes: u64,
+    /// the number of blocks found in the block cache
+    pub block_cache_hit_count: u64,
+    /// the number of memtables probed
+    pub get_from_memtable_count: u64,
+    /// the number of internal keys (e.g. overwritten values) skipped over
+    pub internal_key_skipped_count: u64,
+    /// the number of tombstones skipped over
+    pub internal_delete_skipped_count: u64,
+}
+
+impl PerfStats {
+    fn from_context(context: &mut PerfContext) -> Self {
+        PerfStats {
+            block_read_count: context.metric(PerfMetric::BlockReadCount),
+            block_read_bytes: context.metric(PerfMetric::BlockReadByte),
+            block_cache_hit_count: context.metric(PerfMetric::BlockCacheHitCount),
+            get_from_memtable_count: context.metric(PerfMetric::GetFromMemtableCount),
+            internal_key_skipped_count: context.metric(PerfMetric::InternalKeySkippedCount),
+            internal_delete_skipped_count: context.metric(PerfMetric::InternalDeleteSkippedCount),
+        }
+    }
+}
+
+impl<K, V> DBMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Same as `get`, but also returns the work RocksDB performed to serve the read, to
+    /// diagnose slow queries.
+    ///
+    /// The perf context of RocksDB is per thread, and is only enabled for the duration
+    /// of the read: its collection is not free, so it should not be used on hot paths.
+    pub fn get_with_perf(&self, key: &K) -> (Result<Option<V>, TypedStoreError>, PerfStats) {
+        let mut context = PerfContext::default();
+        set_perf_stats(PerfStatsLevel::EnableCount);
+        context.reset();
+        let result = self.get(key);
+        let stats = PerfStats::from_context(&mut context);
+        set_perf_stats(PerfStatsLevel::Disable);
+        (result, stats)
+    }
+}
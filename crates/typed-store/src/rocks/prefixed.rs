@@ -0,0 +1,148 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::marker::PhantomData;
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, DBMap, DBRawIteratorMultiThreaded, TypedStoreError};
+
+/// A map stored under a namespace of a column family: every key is stored prefixed with
+/// the namespace bytes, so that several logical maps can share a column family when the
+/// number of column families must stay bounded.
+///
+/// The prefix is added and stripped transparently, and iteration never leaves the
+/// namespace. The namespaces sharing a column family must not be prefixes of one another,
+/// which is guaranteed by giving them all the same length.
+pub struct PrefixedDBMap<K, V> {
+    map: DBMap<K, V>,
+    namespace: Vec<u8>,
+}
+
+impl<K, V> PrefixedDBMap<K, V> {
+    pub fn new(map: DBMap<K, V>, namespace: impl Into<Vec<u8>>) -> Self {
+        PrefixedDBMap {
+            map,
+            namespace: namespace.into(),
+        }
+    }
+
+    /// The bytes prepended to the keys of this map.
+    pub fn namespace(&self) -> &[u8] {
+        &self.namespace
+    }
+}
+
+impl<K, V> PrefixedDBMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    fn prefixed<S: Serialize + ?Sized>(&self, key: &S) -> Result<Vec<u8>, TypedStoreError> {
+        let mut key_buf = self.namespace.clone();
+        key_buf.extend(be_fix_int_ser(key)?);
+        Ok(key_buf)
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    pub fn contains_key(&self, key: &K) -> Result<bool, TypedStoreError> {
+        let key_buf = self.prefixed(key)?;
+        Ok(self
+            .map
+            .rocksdb
+            .get_pinned_cf_opt(&self.map.cf(), &key_buf, &self.map.read_options())?
+            .is_some())
+    }
+
+    /// Returns the value for the given key from the map, if it exists.
+    pub fn get(&self, key: &K) -> Result<Option<V>, TypedStoreError> {
+        let key_buf = self.prefixed(key)?;
+        let readopts = self.map.read_options();
+        match self
+            .map
+            .rocksdb
+            .get_pinned_cf_opt(&self.map.cf(), &key_buf, &readopts)?
+        {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Inserts the given key-value pair into the map.
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), TypedStoreError> {
+        let key_buf = self.prefixed(key)?;
+        let value_buf = bincode::serialize(value)?;
+        self.map
+            .rocksdb
+            .put_cf(&self.map.cf(), &key_buf, &value_buf)?;
+        Ok(())
+    }
+
+    /// Removes the entry for the given key from the map.
+    pub fn remove(&self, key: &K) -> Result<(), TypedStoreError> {
+        let key_buf = self.prefixed(key)?;
+        self.map.rocksdb.delete_cf(&self.map.cf(), &key_buf)?;
+        Ok(())
+    }
+
+    /// Returns an iterator over all the entries of the namespace, in ascending key order.
+    pub fn iter(&self) -> PrefixedIter<'_, K, V> {
+        self.iter_prefix_bytes(&self.namespace)
+    }
+
+    /// Returns an iterator over the entries of the namespace whose serialized key starts
+    /// with the serialized `prefix`, see `DBMap::prefix_iter`.
+    pub fn prefix_iter<P: Serialize + ?Sized>(
+        &self,
+        prefix: &P,
+    ) -> Result<PrefixedIter<'_, K, V>, TypedStoreError> {
+        Ok(self.iter_prefix_bytes(&self.prefixed(prefix)?))
+    }
+
+    fn iter_prefix_bytes(&self, prefix: &[u8]) -> PrefixedIter<'_, K, V> {
+        let mut db_iter = self
+            .map
+            .rocksdb
+            .raw_iterator_cf_opt(&self.map.cf(), self.map.prefix_read_options(prefix, None));
+        db_iter.seek_to_first();
+
+        PrefixedIter {
+            db_iter,
+            namespace_len: self.namespace.len(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the key-value pairs of a `PrefixedDBMap`, with the namespace
+/// stripped from the keys.
+pub struct PrefixedIter<'a, K, V> {
+    db_iter: DBRawIteratorMultiThreaded<'a>,
+    namespace_len: usize,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K: DeserializeOwned, V: DeserializeOwned> Iterator for PrefixedIter<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.db_iter.valid() {
+            let config = bincode::DefaultOptions::new()
+                .with_big_endian()
+                .with_fixint_encoding();
+            let key = self
+                .db_iter
+                .key()
+                .and_then(|k| config.deserialize(&k[self.namespace_len..]).ok());
+            let value = self
+                .db_iter
+                .value()
+                .and_then(|v| bincode::deserialize(v).ok());
+
+            self.db_iter.next();
+            key.and_then(|k| value.map(|v| (k, v)))
+        } else {
+            None
+        }
+    }
+}
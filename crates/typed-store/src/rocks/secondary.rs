@@ -0,0 +1,76 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::{marker::PhantomData, path::Path, sync::Arc};
+
+use rocksdb::{DBWithThreadMode, MultiThreaded};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::instrument;
+
+use super::{DBMap, TypedStoreError};
+use crate::traits::Map;
+
+/// A read-only replica of a map, served by a secondary instance of the primary's
+/// database, to take read traffic off the primary.
+///
+/// The replica only observes the writes of the primary as of its last catch-up (or its
+/// opening): it does not follow them on its own.
+pub struct SecondaryDBMap<K, V> {
+    map: DBMap<K, V>,
+}
+
+impl<K, V> SecondaryDBMap<K, V> {
+    /// Opens a secondary instance of the database at `primary_path`, operating under the
+    /// optional column family. The secondary keeps its own logs under `secondary_path`,
+    /// which must differ from the primary's.
+    #[instrument(level="debug", skip_all, fields(primary = ?primary_path.as_ref(), cf = ?opt_cf), err)]
+    pub fn open<P: AsRef<Path>>(
+        primary_path: P,
+        secondary_path: P,
+        db_options: Option<rocksdb::Options>,
+        opt_cf: Option<&str>,
+    ) -> Result<Self, TypedStoreError> {
+        let mut options = db_options.unwrap_or_default();
+        // a secondary instance must keep all the files of the primary open
+        options.set_max_open_files(-1);
+        let cf_key = opt_cf.unwrap_or(rocksdb::DEFAULT_COLUMN_FAMILY_NAME);
+        let mut cfs = vec![rocksdb::DEFAULT_COLUMN_FAMILY_NAME];
+        if cf_key != rocksdb::DEFAULT_COLUMN_FAMILY_NAME {
+            cfs.push(cf_key);
+        }
+        let rocksdb = DBWithThreadMode::<MultiThreaded>::open_cf_as_secondary(
+            &options,
+            primary_path,
+            secondary_path,
+            cfs,
+        )?;
+
+        Ok(SecondaryDBMap {
+            map: DBMap {
+                rocksdb: Arc::new(rocksdb),
+                _phantom: PhantomData,
+                cf: cf_key.to_string(),
+                verify_checksums: true,
+            },
+        })
+    }
+
+    /// Replays the writes the primary made since the last catch-up, so that they become
+    /// visible to the reads of the replica.
+    #[instrument(level = "debug", skip_all, err)]
+    pub fn catch_up_with_primary(&self) -> Result<(), TypedStoreError> {
+        self.map.rocksdb.try_catch_up_with_primary()?;
+        Ok(())
+    }
+}
+
+impl<K, V> SecondaryDBMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Returns the value of the key on the replica, without catching up with the primary
+    /// first: the value may be stale, as of the last `catch_up_with_primary`.
+    pub fn get_stale(&self, key: &K) -> Result<Option<V>, TypedStoreError> {
+        self.map.get(key)
+    }
+}
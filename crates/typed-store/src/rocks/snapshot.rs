@@ -0,0 +1,71 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::sync::Arc;
+
+use rocksdb::{DBWithThreadMode, MultiThreaded, SnapshotWithThreadMode};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, DBMap, TypedStoreError};
+
+/// A snapshot of a whole database, through which the maps sharing that database are read
+/// as of the same instant, e.g. to read related data from several tables consistently.
+///
+/// The snapshot keeps the versions of the entries written after it alive, which costs
+/// space until it is dropped.
+pub struct MultiSnapshot<'a> {
+    db: &'a Arc<DBWithThreadMode<MultiThreaded>>,
+    snapshot: SnapshotWithThreadMode<'a, DBWithThreadMode<MultiThreaded>>,
+}
+
+impl<'a> MultiSnapshot<'a> {
+    /// Takes a snapshot of the database, e.g. as returned by `open_cf`.
+    pub fn new(db: &'a Arc<DBWithThreadMode<MultiThreaded>>) -> Self {
+        MultiSnapshot {
+            db,
+            snapshot: db.snapshot(),
+        }
+    }
+
+    /// Returns the value the key had in the map when the snapshot was taken. The map
+    /// must belong to the snapshotted database.
+    pub fn get<K, V>(&self, map: &DBMap<K, V>, key: &K) -> Result<Option<V>, TypedStoreError>
+    where
+        K: Serialize,
+        V: DeserializeOwned,
+    {
+        if !Arc::ptr_eq(&map.rocksdb, self.db) {
+            return Err(TypedStoreError::CrossDBSnapshot);
+        }
+        let key_buf = be_fix_int_ser(key)?;
+        match self
+            .snapshot
+            .get_cf_opt(&map.cf(), &key_buf, map.read_options())?
+        {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns true if the map held a value for the key when the snapshot was taken.
+    pub fn contains_key<K, V>(&self, map: &DBMap<K, V>, key: &K) -> Result<bool, TypedStoreError>
+    where
+        K: Serialize,
+    {
+        if !Arc::ptr_eq(&map.rocksdb, self.db) {
+            return Err(TypedStoreError::CrossDBSnapshot);
+        }
+        let key_buf = be_fix_int_ser(key)?;
+        Ok(self
+            .snapshot
+            .get_cf_opt(&map.cf(), &key_buf, map.read_options())?
+            .is_some())
+    }
+}
+
+impl<K, V> DBMap<K, V> {
+    /// Takes a snapshot of the whole database of this map, through which this map and
+    /// the others sharing its database are read consistently, see `MultiSnapshot`.
+    pub fn snapshot(&self) -> MultiSnapshot<'_> {
+        MultiSnapshot::new(&self.rocksdb)
+    }
+}
@@ -502,3 +502,1743 @@ fn test_multi_remove() {
         assert_eq!(Some(v), val);
     }
 }
+
+#[test]
+fn test_drop_cf() {
+    let path = temp_dir();
+    let rocks = open_cf(&path, None, &["First_CF", "Second_CF"]).unwrap();
+
+    let db =
+        DBMap::<u32, String>::reopen(&rocks, Some("First_CF")).expect("Failed to open storage");
+    db.insert(&123456789, &"123456789".to_string())
+        .expect("Failed to insert");
+    db.drop_cf().expect("Failed to drop column family");
+
+    let cfs =
+        rocksdb::DBWithThreadMode::<MultiThreaded>::list_cf(&rocksdb::Options::default(), &path)
+            .expect("Failed to list column families");
+    assert!(!cfs.contains(&"First_CF".to_string()));
+    assert!(cfs.contains(&"Second_CF".to_string()));
+    assert!(DBMap::<u32, String>::reopen(&rocks, Some("First_CF")).is_err());
+}
+
+#[test]
+fn test_drop_column_family() {
+    let path = temp_dir();
+    let rocks = open_cf(&path, None, &["First_CF", "Second_CF"]).unwrap();
+
+    drop_column_family(&rocks, "Second_CF").expect("Failed to drop column family");
+
+    let cfs =
+        rocksdb::DBWithThreadMode::<MultiThreaded>::list_cf(&rocksdb::Options::default(), &path)
+            .expect("Failed to list column families");
+    assert!(cfs.contains(&"First_CF".to_string()));
+    assert!(!cfs.contains(&"Second_CF".to_string()));
+}
+
+#[test]
+fn test_range_iter() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    // insert out of order
+    for i in [7, 1, 9, 3, 5, 10, 2, 8, 4, 6] {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+
+    let key_vals: Vec<_> = db.range_iter(&3, &7).expect("Range failed").collect();
+    assert_eq!(
+        key_vals,
+        (3..7).map(|i| (i, i.to_string())).collect::<Vec<_>>()
+    );
+
+    // Bounds outside the stored keys
+    assert_eq!(db.range_iter(&0, &100).expect("Range failed").count(), 10);
+    assert_eq!(db.range_iter(&11, &100).expect("Range failed").count(), 0);
+    // Empty range
+    assert_eq!(db.range_iter(&5, &5).expect("Range failed").count(), 0);
+}
+
+#[test]
+fn test_range_iter_rev() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    for i in 1..=10 {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+
+    let key_vals: Vec<_> = db.range_iter_rev(&3, &7).expect("Range failed").collect();
+    assert_eq!(
+        key_vals,
+        (3..7).rev().map(|i| (i, i.to_string())).collect::<Vec<_>>()
+    );
+
+    // The end bound is exclusive even when it is the last key
+    let keys: Vec<_> = db
+        .range_iter_rev(&8, &10)
+        .expect("Range failed")
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, vec![9, 8]);
+
+    // Bounds outside the stored keys
+    let keys: Vec<_> = db
+        .range_iter_rev(&0, &100)
+        .expect("Range failed")
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, (1..=10).rev().collect::<Vec<_>>());
+    assert_eq!(
+        db.range_iter_rev(&11, &100).expect("Range failed").count(),
+        0
+    );
+    assert_eq!(db.range_iter_rev(&5, &5).expect("Range failed").count(), 0);
+}
+
+#[test]
+fn test_ttl_read_after_expiry() {
+    let db =
+        DBMap::<i32, (u64, String)>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let raw = db.clone();
+    let ttl_db = TtlDBMap::new(db, std::time::Duration::from_secs(3600));
+
+    ttl_db
+        .insert(&1, &"1".to_string())
+        .expect("Failed to insert");
+    ttl_db
+        .insert_with_ttl(&2, &"2".to_string(), std::time::Duration::ZERO)
+        .expect("Failed to insert");
+
+    assert_eq!(
+        ttl_db.get(&1).expect("Failed to get"),
+        Some("1".to_string())
+    );
+    assert!(ttl_db.contains_key(&1).expect("Failed to get"));
+    // the expired entry reads as absent
+    assert_eq!(ttl_db.get(&2).expect("Failed to get"), None);
+    assert!(!ttl_db.contains_key(&2).expect("Failed to get"));
+    // but is still stored, as it is not evicted on read by default
+    assert!(raw.contains_key(&2).expect("Failed to get"));
+}
+
+#[test]
+fn test_ttl_evict_on_read() {
+    let db =
+        DBMap::<i32, (u64, String)>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let raw = db.clone();
+    let ttl_db = TtlDBMap::new(db, std::time::Duration::ZERO).evict_on_read(true);
+
+    ttl_db
+        .insert(&1, &"1".to_string())
+        .expect("Failed to insert");
+    assert!(raw.contains_key(&1).expect("Failed to get"));
+
+    assert_eq!(ttl_db.get(&1).expect("Failed to get"), None);
+    assert!(!raw.contains_key(&1).expect("Failed to get"));
+}
+
+#[test]
+fn test_ttl_evict_on_read_keeps_rewritten_entry() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let db =
+        DBMap::<i32, (u64, String)>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let raw = db.clone();
+    let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    // rewrites the entry right after the expired one is read, once
+    let writer = std::sync::Mutex::new(Some(db.clone()));
+    let clock = move || {
+        if let Some(db) = writer.lock().unwrap().take() {
+            let expiry = (now + Duration::from_secs(60))
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            db.insert(&1, &(expiry, "fresh".to_string()))
+                .expect("Failed to insert");
+        }
+        now
+    };
+    let ttl_db = TtlDBMap::new(db, Duration::from_secs(60))
+        .evict_on_read(true)
+        .with_clock(std::sync::Arc::new(clock));
+    raw.insert(&1, &(0, "stale".to_string()))
+        .expect("Failed to insert");
+
+    // the expired entry is not deleted, as it was rewritten before the deletion
+    assert_eq!(ttl_db.get(&1).expect("Failed to get"), None);
+    assert_eq!(
+        ttl_db.get(&1).expect("Failed to get"),
+        Some("fresh".to_string())
+    );
+}
+
+#[test]
+fn test_ttl_purge_expired() {
+    let db =
+        DBMap::<i32, (u64, String)>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let raw = db.clone();
+    let ttl_db = TtlDBMap::new(db, std::time::Duration::from_secs(3600));
+
+    for i in 0..10 {
+        let ttl = if i % 2 == 0 {
+            std::time::Duration::ZERO
+        } else {
+            std::time::Duration::from_secs(3600)
+        };
+        ttl_db
+            .insert_with_ttl(&i, &i.to_string(), ttl)
+            .expect("Failed to insert");
+    }
+    assert_eq!(raw.iter().count(), 10);
+
+    assert_eq!(ttl_db.purge_expired().expect("Failed to purge"), 5);
+    assert_eq!(raw.keys().collect::<Vec<_>>(), vec![1, 3, 5, 7, 9]);
+    for i in 0..10 {
+        assert_eq!(
+            ttl_db.get(&i).expect("Failed to get"),
+            (i % 2 == 1).then(|| i.to_string())
+        );
+    }
+
+    // nothing left to purge
+    assert_eq!(ttl_db.purge_expired().expect("Failed to purge"), 0);
+}
+
+#[test]
+fn test_shared_env() {
+    let env = shared_env(2, 1).expect("Failed to create env");
+    let mut options = rocksdb::Options::default();
+    options.set_env(&env);
+
+    let db_1 =
+        DBMap::open(temp_dir(), Some(options.clone()), None).expect("Failed to open storage");
+    let db_2 =
+        DBMap::open(temp_dir(), Some(options), Some("Second_CF")).expect("Failed to open storage");
+
+    db_1.insert(&123456789, &"123456789".to_string())
+        .expect("Failed to insert");
+    db_2.insert(&123456789, &"987654321".to_string())
+        .expect("Failed to insert");
+
+    assert_eq!(
+        db_1.get(&123456789).expect("Failed to get"),
+        Some("123456789".to_string())
+    );
+    assert_eq!(
+        db_2.get(&123456789).expect("Failed to get"),
+        Some("987654321".to_string())
+    );
+}
+
+#[test]
+fn test_live_data_size() {
+    let db = DBMap::<i32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let keys: Vec<_> = (0..1000).collect();
+    db.multi_insert(keys.iter().map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+    db.flush().expect("Failed to flush");
+
+    let size = db.live_data_size().expect("Failed to get data size");
+    assert!(size.total > 0);
+    assert_eq!(size.reclaimable(), 0);
+
+    // The tombstones land in a new SST file, overlapping the first one
+    db.multi_remove(keys.iter()).expect("Failed to remove");
+    db.flush().expect("Failed to flush");
+
+    let size = db.live_data_size().expect("Failed to get data size");
+    assert!(size.total > size.live);
+}
+
+#[test]
+fn test_prefix_iter() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    for group in [u32::MAX - 1, u32::MAX, 0, 1] {
+        for i in 0..10u32 {
+            db.insert(&(group, i), &i.to_string())
+                .expect("Failed to insert");
+        }
+    }
+
+    let keys: Vec<_> = db
+        .prefix_iter(&(u32::MAX - 1))
+        .expect("Prefix iteration failed")
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, (0..10).map(|i| (u32::MAX - 1, i)).collect::<Vec<_>>());
+
+    // A prefix made of 0xff bytes only has no upper bound
+    assert_eq!(
+        db.prefix_iter(&u32::MAX)
+            .expect("Prefix iteration failed")
+            .count(),
+        10
+    );
+    assert_eq!(
+        db.prefix_iter(&2u32)
+            .expect("Prefix iteration failed")
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn test_prefix_page() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    for group in [1u32, 2, 3] {
+        for i in 0..10u32 {
+            db.insert(&(group, i), &i.to_string())
+                .expect("Failed to insert");
+        }
+    }
+
+    let (page, cursor) = db.prefix_page(&2u32, None, 4).expect("Paging failed");
+    assert_eq!(
+        page,
+        (0..4).map(|i| ((2, i), i.to_string())).collect::<Vec<_>>()
+    );
+    assert_eq!(cursor, Some((2, 3)));
+
+    let (page, cursor) = db
+        .prefix_page(&2u32, Some(&(2, 7)), 4)
+        .expect("Paging failed");
+    assert_eq!(
+        page,
+        (8..10).map(|i| ((2, i), i.to_string())).collect::<Vec<_>>()
+    );
+    assert_eq!(cursor, None);
+
+    // an empty page would never make progress
+    assert_eq!(
+        db.prefix_page(&2u32, None, 0),
+        Err(TypedStoreError::InvalidScanBounds)
+    );
+}
+
+#[test]
+fn test_ttl_touch() {
+    let db =
+        DBMap::<i32, (u64, String)>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let raw = db.clone();
+    let ttl_db = TtlDBMap::new(db, std::time::Duration::from_secs(3600));
+
+    ttl_db
+        .insert_with_ttl(&1, &"1".to_string(), std::time::Duration::from_secs(60))
+        .expect("Failed to insert");
+    ttl_db
+        .insert_with_ttl(&2, &"2".to_string(), std::time::Duration::ZERO)
+        .expect("Failed to insert");
+    let (expiry, _) = raw.get(&1).expect("Failed to get").unwrap();
+
+    assert!(ttl_db.touch(&1).expect("Failed to touch"));
+    let (touched_expiry, value) = raw.get(&1).expect("Failed to get").unwrap();
+    assert!(touched_expiry > expiry);
+    assert_eq!(value, "1".to_string());
+
+    // neither expired nor missing keys are touched
+    assert!(!ttl_db.touch(&2).expect("Failed to touch"));
+    assert_eq!(ttl_db.get(&2).expect("Failed to get"), None);
+    assert!(!ttl_db.touch(&3).expect("Failed to touch"));
+    assert!(!raw.contains_key(&3).expect("Failed to get"));
+}
+
+#[test]
+fn test_snapshot_export_import() {
+    let db = DBMap::<i32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..100).map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+
+    let snapshot_dir = temp_dir().join("snapshot");
+    db.export_snapshot(&snapshot_dir)
+        .expect("Failed to export snapshot");
+
+    // import into a map of a database using different options
+    let mut options = rocksdb::Options::default();
+    options.set_compression_type(rocksdb::DBCompressionType::Zstd);
+    let other = DBMap::<i32, String>::open(temp_dir(), Some(options), Some("Other_CF"))
+        .expect("Failed to open storage");
+    other
+        .insert(&1000, &"1000".to_string())
+        .expect("Failed to insert");
+    other
+        .import_snapshot(&snapshot_dir)
+        .expect("Failed to import snapshot");
+
+    let imported: Vec<_> = other.iter().collect();
+    let expected: Vec<_> = (0..100)
+        .chain(std::iter::once(1000))
+        .map(|i| (i, i.to_string()))
+        .collect();
+    assert_eq!(imported, expected);
+}
+
+#[test]
+fn test_get_raw_pinned() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    let value = (7u64, vec![1u8; 1000]);
+    db.insert(&1, &value).expect("Failed to insert");
+
+    let pinned = db
+        .get_raw_pinned(&1)
+        .expect("Failed to get")
+        .expect("Value should exist");
+    // the value is serialized as is, its header is the leading u64
+    assert_eq!(&pinned[..8], &7u64.to_le_bytes()[..]);
+    let header: u64 = bincode::deserialize(&pinned).expect("Failed to deserialize");
+    assert_eq!(header, 7);
+    drop(pinned);
+
+    assert!(db.get_raw_pinned(&2).expect("Failed to get").is_none());
+}
+
+#[test]
+fn test_compaction_filter() {
+    // drop the entries whose value is an odd number
+    let options = DBMapOptions::new().with_compaction_filter("odd", |_key, value| {
+        bincode::deserialize::<u64>(value).map_or(false, |v| v % 2 == 1)
+    });
+    let db = DBMap::<u64, u64>::open(temp_dir(), Some(options.into()), None)
+        .expect("Failed to open storage");
+    db.multi_insert((0..100).map(|i| (i, i)))
+        .expect("Failed to insert");
+    db.flush().expect("Failed to flush");
+
+    // filtering happens on compaction only
+    assert_eq!(db.iter().count(), 100);
+    db.rocksdb
+        .compact_range_cf(&db.cf(), None::<&[u8]>, None::<&[u8]>);
+
+    let remaining: Vec<_> = db.keys().collect();
+    assert_eq!(remaining, (0..100).step_by(2).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_scan_stats() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    // u64 keys are serialized on 8 bytes, and the strings on 8 bytes of length + their bytes
+    for i in 0..10u64 {
+        db.insert(&i, &"x".repeat(i as usize))
+            .expect("Failed to insert");
+    }
+
+    let mut iter = db.iter();
+    assert_eq!(iter.scan_stats(), ScanStats::default());
+    assert_eq!(iter.by_ref().count(), 10);
+    assert_eq!(
+        iter.scan_stats(),
+        ScanStats {
+            entries: 10,
+            key_bytes: 10 * 8,
+            value_bytes: (0..10).map(|i| 8 + i).sum(),
+        }
+    );
+
+    let mut rev_iter = db.range_iter_rev(&0, &5).expect("Range failed");
+    assert_eq!(rev_iter.by_ref().count(), 5);
+    assert_eq!(rev_iter.scan_stats().entries, 5);
+    assert_eq!(rev_iter.scan_stats().key_bytes, 5 * 8);
+}
+
+#[test]
+fn test_composite_key_prefix() {
+    let db = DBMap::<CompositeKey<u32, String>, u64>::open(temp_dir(), None, None)
+        .expect("Failed to open storage");
+    for first in [1u32, 2, 256, 3] {
+        for second in ["b", "a", "c"] {
+            db.insert(&CompositeKey::new(first, second.to_string()), &1)
+                .expect("Failed to insert");
+        }
+    }
+
+    let keys: Vec<_> = db
+        .prefix_iter(&CompositeKey::<u32, String>::prefix(2))
+        .expect("Prefix iteration failed")
+        .map(|(key, _)| key)
+        .collect();
+    let expected: Vec<_> = ["a", "b", "c"]
+        .into_iter()
+        .map(|second| CompositeKey::new(2, second.to_string()))
+        .collect();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_composite_key_variable_size_prefix() {
+    let db = DBMap::<CompositeKey<String, u64>, u64>::open(temp_dir(), None, None)
+        .expect("Failed to open storage");
+    for first in ["ab", "abc", "a"] {
+        for second in 0..3 {
+            db.insert(&CompositeKey::new(first.to_string(), second), &second)
+                .expect("Failed to insert");
+        }
+    }
+
+    let keys: Vec<_> = db
+        .prefix_iter(&CompositeKey::<String, u64>::prefix("ab".to_string()))
+        .expect("Prefix iteration failed")
+        .map(|(key, _)| key)
+        .collect();
+    let expected: Vec<_> = (0..3)
+        .map(|second| CompositeKey::new("ab".to_string(), second))
+        .collect();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_max_open_files() {
+    let options = DBMapOptions::new().max_open_files(20);
+    let db = DBMap::open(temp_dir(), Some(options.into()), None).expect("Failed to open storage");
+
+    // spread the data over many SST files
+    for i in 0..50 {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+        db.flush().expect("Failed to flush");
+    }
+
+    for i in 0..50 {
+        assert_eq!(db.get(&i).expect("Failed to get"), Some(i.to_string()));
+    }
+}
+
+#[test]
+fn test_level0_triggers() {
+    let options = DBMapOptions::new()
+        .level0_file_num_compaction_trigger(8)
+        .level0_slowdown_writes_trigger(64)
+        .level0_stop_writes_trigger(128);
+    let db = DBMap::open(temp_dir(), Some(options.into()), None).expect("Failed to open storage");
+
+    // a burst of writes piling up level-0 files
+    for i in 0..100 {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+        db.flush().expect("Failed to flush");
+    }
+
+    for i in 0..100 {
+        assert_eq!(db.get(&i).expect("Failed to get"), Some(i.to_string()));
+    }
+}
+
+#[test]
+fn test_statistics() {
+    let db = DBMap::<u32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    assert_eq!(db.statistics_string(), Ok(None));
+
+    let options = DBMapOptions::new().enable_statistics();
+    let db = DBMap::<u32, String>::open(temp_dir(), Some(options.into()), None)
+        .expect("Failed to open storage");
+    for i in 0..10 {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+        assert_eq!(db.get(&i).expect("Failed to get"), Some(i.to_string()));
+    }
+
+    let statistics = db
+        .statistics_string()
+        .expect("Failed to read the statistics")
+        .expect("The statistics are enabled");
+    assert!(statistics.contains("rocksdb.block.cache.hit"));
+    assert!(statistics.contains("rocksdb.number.keys.written COUNT : 10"));
+    assert!(statistics.contains("rocksdb.db.get.micros"));
+}
+
+#[test]
+fn test_bottommost_compression() {
+    let options = DBMapOptions::new().bottommost_compression(rocksdb::DBCompressionType::Zstd);
+    let db = DBMap::<u32, String>::open(temp_dir(), Some(options.into()), Some("table"))
+        .expect("Failed to open storage");
+    let value = "cold".repeat(100);
+    db.multi_insert((0..1000).map(|i| (i, value.clone())))
+        .expect("Failed to insert");
+
+    // compact everything down to the bottommost level
+    db.rocksdb
+        .compact_range_cf(&db.cf(), None::<&[u8]>, None::<&[u8]>);
+    let stats = db.level_stats().expect("Failed to read the level stats");
+    assert!(stats.iter().all(|stat| stat.level > 0));
+
+    assert_eq!(db.get(&500), Ok(Some(value.clone())));
+    db.insert(&1000, &value).expect("Failed to insert");
+    assert_eq!(db.iter().count(), 1001);
+}
+
+#[test]
+fn test_memtable_whole_key_filtering() {
+    let options = DBMapOptions::new()
+        .memtable_prefix_bloom_ratio(0.1)
+        .memtable_whole_key_filtering(true);
+    let db = DBMap::<u32, String>::open(temp_dir(), Some(options.into()), Some("table"))
+        .expect("Failed to open storage");
+    db.multi_insert((0..100).map(|i| (2 * i, i.to_string())))
+        .expect("Failed to insert");
+
+    // the entries are still in the memtable, present or not
+    for i in 0..100 {
+        assert_eq!(db.get(&(2 * i)), Ok(Some(i.to_string())));
+        assert_eq!(db.get(&(2 * i + 1)), Ok(None));
+    }
+    assert_eq!(db.contains_key(&1000), Ok(false));
+}
+
+#[test]
+fn test_unordered_write() {
+    let options = DBMapOptions::new().unordered_write(true);
+    let db = DBMap::<u32, u32>::open(temp_dir(), Some(options.into()), None)
+        .expect("Failed to open storage");
+
+    // a single writer still reads its own writes, in order
+    for i in 0..100 {
+        db.insert(&(i % 10), &i).expect("Failed to insert");
+        assert_eq!(db.get(&(i % 10)), Ok(Some(i)));
+    }
+    db.remove(&0).expect("Failed to remove");
+    db.multi_insert([(20, 20), (21, 21)])
+        .expect("Failed to insert");
+    assert_eq!(
+        db.iter().collect::<Vec<_>>(),
+        (1..10)
+            .map(|i| (i, 90 + i))
+            .chain([(20, 20), (21, 21)])
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_reinterpret() {
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    let db = DBMap::<u32, (u32, u32)>::open(temp_dir(), None, Some("table"))
+        .expect("Failed to open storage");
+    db.insert(&1, &(3, 4)).expect("Failed to insert");
+
+    // a struct encodes as the tuple of its fields
+    let points = db.reinterpret::<Point>();
+    assert_eq!(points.get(&1), Ok(Some(Point { x: 3, y: 4 })));
+    points
+        .insert(&2, &Point { x: 5, y: 6 })
+        .expect("Failed to insert");
+    assert_eq!(db.get(&2), Ok(Some((5, 6))));
+    assert_eq!(points.iter().count(), 2);
+}
+
+#[test]
+fn test_warmup() {
+    let path = temp_dir();
+    let db = DBMap::<i32, String>::open(&path, None, None).expect("Failed to open storage");
+    db.multi_insert((0..100).map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+    db.flush().expect("Failed to flush");
+    drop(db);
+
+    // reopened with a cold cache
+    let db = DBMap::<i32, String>::open(&path, None, None).expect("Failed to open storage");
+    let keys: Vec<_> = (0..100).step_by(10).chain(std::iter::once(1000)).collect();
+    db.warmup(&keys).expect("Failed to warm up");
+
+    for i in (0..100).step_by(10) {
+        assert_eq!(db.get(&i).expect("Failed to get"), Some(i.to_string()));
+    }
+}
+
+#[test]
+fn test_multi_map() {
+    let db = DBMap::<CompositeKey<u32, String>, ()>::open(temp_dir(), None, None)
+        .expect("Failed to open storage");
+    let multi_map = MultiMap::new(db);
+
+    for tag in ["red", "blue", "green"] {
+        multi_map
+            .insert(&1, &tag.to_string())
+            .expect("Failed to insert");
+    }
+    multi_map
+        .insert(&1, &"red".to_string())
+        .expect("Failed to insert");
+    multi_map
+        .insert(&2, &"red".to_string())
+        .expect("Failed to insert");
+
+    // the strings are serialized after their length, which they sort by first
+    assert_eq!(
+        multi_map.get_all(&1).expect("Failed to get"),
+        vec!["red".to_string(), "blue".to_string(), "green".to_string()]
+    );
+    assert!(multi_map
+        .contains(&1, &"green".to_string())
+        .expect("Failed to get"));
+
+    multi_map
+        .remove(&1, &"green".to_string())
+        .expect("Failed to remove");
+    assert_eq!(
+        multi_map.get_all(&1).expect("Failed to get"),
+        vec!["red".to_string(), "blue".to_string()]
+    );
+    assert!(!multi_map
+        .contains(&1, &"green".to_string())
+        .expect("Failed to get"));
+    // the other keys are left alone
+    assert_eq!(
+        multi_map.get_all(&2).expect("Failed to get"),
+        vec!["red".to_string()]
+    );
+    assert!(multi_map.get_all(&3).expect("Failed to get").is_empty());
+}
+
+#[test]
+fn test_floor_ceil() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    for i in [10u64, 20, 30] {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+
+    // between stored entries
+    assert_eq!(
+        db.floor(&25).expect("Failed to seek"),
+        Some((20, "20".to_string()))
+    );
+    assert_eq!(
+        db.ceil(&25).expect("Failed to seek"),
+        Some((30, "30".to_string()))
+    );
+    // on a stored entry
+    assert_eq!(
+        db.floor(&20).expect("Failed to seek"),
+        Some((20, "20".to_string()))
+    );
+    assert_eq!(
+        db.ceil(&20).expect("Failed to seek"),
+        Some((20, "20".to_string()))
+    );
+    // outside of the stored entries
+    assert_eq!(db.floor(&5).expect("Failed to seek"), None);
+    assert_eq!(
+        db.ceil(&5).expect("Failed to seek"),
+        Some((10, "10".to_string()))
+    );
+    assert_eq!(
+        db.floor(&35).expect("Failed to seek"),
+        Some((30, "30".to_string()))
+    );
+    assert_eq!(db.ceil(&35).expect("Failed to seek"), None);
+}
+
+#[test]
+fn test_last_key() {
+    let path = temp_dir();
+    let db = DBMap::<u64, String>::open(&path, None, None).expect("Failed to open storage");
+    assert_eq!(db.last_key().expect("Failed to seek"), None);
+    for i in [10u64, 30, 20] {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+    assert_eq!(db.last_key().expect("Failed to seek"), Some(30));
+    drop(db);
+
+    // a last key of another type fails to decode
+    let db = DBMap::<[u8; 9], String>::open(&path, None, None).expect("Failed to open storage");
+    db.insert(&[0xff; 9], &"garbage".to_string())
+        .expect("Failed to insert");
+    drop(db);
+    let db = DBMap::<u64, String>::open(&path, None, None).expect("Failed to open storage");
+    assert!(matches!(
+        db.last_key(),
+        Err(TypedStoreError::SerializationError(_))
+    ));
+}
+
+#[test]
+fn test_counter_concurrent_increments() {
+    let options = DBMapOptions::new().with_counter_merge_operator();
+    let db = DBMap::<u32, i64>::open(temp_dir(), Some(options.into()), None)
+        .expect("Failed to open storage");
+    let counter = std::sync::Arc::new(Counter::new(db));
+    assert_eq!(counter.get(&1).expect("Failed to get"), 0);
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let counter = counter.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    counter.increment(&1, 2).expect("Failed to increment");
+                    counter.increment(&2, -1).expect("Failed to increment");
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("Incrementing thread panicked");
+    }
+
+    assert_eq!(counter.get(&1).expect("Failed to get"), 16000);
+    assert_eq!(counter.get(&2).expect("Failed to get"), -8000);
+
+    counter.reset(&1).expect("Failed to reset");
+    counter.increment(&1, 5).expect("Failed to increment");
+    assert_eq!(counter.get(&1).expect("Failed to get"), 5);
+}
+
+#[test]
+fn test_hash_memtable() {
+    let options = DBMapOptions::new().with_hash_memtable(8, 1024);
+    let db = DBMap::<u64, String>::open(temp_dir(), Some(options.into()), None)
+        .expect("Failed to open storage");
+
+    for i in 0..100 {
+        db.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+    assert_eq!(db.get(&42).expect("Failed to get"), Some("42".to_string()));
+
+    // point lookups keep working once the memtable is flushed
+    db.flush().expect("Failed to flush");
+    db.insert(&100, &"100".to_string())
+        .expect("Failed to insert");
+    assert_eq!(db.get(&42).expect("Failed to get"), Some("42".to_string()));
+    assert_eq!(
+        db.get(&100).expect("Failed to get"),
+        Some("100".to_string())
+    );
+    assert_eq!(db.get(&1000).expect("Failed to get"), None);
+}
+
+#[test]
+fn test_batch_writer() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    let mut writer = db.batch_writer(10);
+
+    for i in 0..9 {
+        writer.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+    // nothing is committed before the batch is full
+    assert_eq!(writer.commits(), 0);
+    assert_eq!(writer.pending(), 9);
+    assert!(!db.contains_key(&0).expect("Failed to get"));
+
+    writer
+        .insert(&9, &"9".to_string())
+        .expect("Failed to insert");
+    assert_eq!(writer.commits(), 1);
+    assert_eq!(writer.pending(), 0);
+    assert!(db.contains_key(&9).expect("Failed to get"));
+
+    for i in 10..25 {
+        writer.insert(&i, &i.to_string()).expect("Failed to insert");
+    }
+    writer.remove(&0).expect("Failed to remove");
+    assert_eq!(writer.commits(), 2);
+    assert_eq!(writer.pending(), 6);
+
+    // a manual flush commits a partial batch
+    writer.flush_batch().expect("Failed to flush");
+    assert_eq!(writer.commits(), 3);
+    writer
+        .insert(&25, &"25".to_string())
+        .expect("Failed to insert");
+    assert_eq!(writer.finish().expect("Failed to finish"), 4);
+
+    assert!(!db.contains_key(&0).expect("Failed to get"));
+    assert_eq!(db.keys().collect::<Vec<_>>(), (1..26).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_total_size_on_disk() {
+    let rocks = open_cf(temp_dir(), None, &["First_CF", "Second_CF"]).unwrap();
+    let (db_1, db_2) = reopen!(&rocks, "First_CF";<u64, String>, "Second_CF";<u64, String>);
+
+    for db in [&db_1, &db_2] {
+        db.multi_insert((0..1000).map(|i| (i, i.to_string())))
+            .expect("Failed to insert");
+        db.flush().expect("Failed to flush");
+    }
+    // and some data that only lives in the write-ahead log
+    db_1.insert(&1000, &"1000".to_string())
+        .expect("Failed to insert");
+
+    let total = total_size_on_disk(&rocks).expect("Failed to get size");
+    let size_1 = db_1.live_data_size().expect("Failed to get size").total;
+    let size_2 = db_2.live_data_size().expect("Failed to get size").total;
+    assert!(size_1 > 0 && size_2 > 0);
+    assert!(total > size_1 + size_2);
+}
+
+#[test]
+fn test_prefixed_namespaces() {
+    let rocks = open_cf(temp_dir(), None, &["Shared_CF"]).unwrap();
+    let (db_1, db_2) =
+        reopen!(&rocks, "Shared_CF";<(u32, u32), String>, "Shared_CF";<(u32, u32), String>);
+    let users = PrefixedDBMap::new(db_1, *b"usr");
+    let items = PrefixedDBMap::new(db_2, *b"itm");
+
+    for i in 0..5 {
+        users
+            .insert(&(1, i), &format!("user-{i}"))
+            .expect("Failed to insert");
+        items
+            .insert(&(1, i), &format!("item-{i}"))
+            .expect("Failed to insert");
+    }
+    items
+        .insert(&(2, 0), &"item-2-0".to_string())
+        .expect("Failed to insert");
+
+    // the same key holds a different value in each namespace
+    assert_eq!(
+        users.get(&(1, 0)).expect("Failed to get"),
+        Some("user-0".to_string())
+    );
+    assert_eq!(
+        items.get(&(1, 0)).expect("Failed to get"),
+        Some("item-0".to_string())
+    );
+    assert!(!users.contains_key(&(2, 0)).expect("Failed to get"));
+
+    // iteration stays within the namespace, and strips it from the keys
+    let user_entries: Vec<_> = users.iter().collect();
+    assert_eq!(
+        user_entries,
+        (0..5)
+            .map(|i| ((1, i), format!("user-{i}")))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(items.iter().count(), 6);
+    assert_eq!(
+        items
+            .prefix_iter(&2u32)
+            .expect("Prefix iteration failed")
+            .collect::<Vec<_>>(),
+        vec![((2, 0), "item-2-0".to_string())]
+    );
+
+    users.remove(&(1, 0)).expect("Failed to remove");
+    assert_eq!(users.iter().count(), 4);
+    assert!(items.contains_key(&(1, 0)).expect("Failed to get"));
+}
+
+#[test]
+fn test_flush_wal() {
+    let path = temp_dir();
+    let db = DBMap::<i32, String>::open(&path, None, None).expect("Failed to open storage");
+    db.multi_insert((0..100).map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+
+    db.flush_wal(true).expect("Failed to flush WAL");
+    db.flush_wal(false).expect("Failed to flush WAL");
+
+    // the writes are recovered from the log on reopening
+    drop(db);
+    let db = DBMap::<i32, String>::open(&path, None, None).expect("Failed to open storage");
+    assert_eq!(db.get(&99).expect("Failed to get"), Some("99".to_string()));
+}
+
+#[test]
+fn test_get_with_perf() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.insert(&123456789, &"123456789".to_string())
+        .expect("Failed to insert");
+
+    // the value is read from the memtable
+    let (result, stats) = db.get_with_perf(&123456789);
+    assert_eq!(
+        result.expect("Failed to get"),
+        Some("123456789".to_string())
+    );
+    assert!(stats.get_from_memtable_count > 0);
+    assert_eq!(stats.block_read_count + stats.block_cache_hit_count, 0);
+
+    // once flushed, the value is read from an SST file
+    db.flush().expect("Failed to flush");
+    let (result, stats) = db.get_with_perf(&123456789);
+    assert_eq!(
+        result.expect("Failed to get"),
+        Some("123456789".to_string())
+    );
+    assert!(stats.block_read_count + stats.block_cache_hit_count > 0);
+}
+
+#[test]
+fn test_open_in_memory() {
+    let options = DBMapOptions::new().with_counter_merge_operator();
+    let db = DBMap::<u32, i64>::open_in_memory(Some(options.into()), Some("counters"))
+        .expect("Failed to open storage");
+    let counter = Counter::new(db.clone());
+
+    // the merge operator runs as on a database on disk
+    counter.increment(&1, 2).expect("Failed to increment");
+    counter.increment(&1, 3).expect("Failed to increment");
+    assert_eq!(counter.get(&1).expect("Failed to get"), 5);
+    db.flush().expect("Failed to flush");
+    assert_eq!(counter.get(&1).expect("Failed to get"), 5);
+
+    // each in-memory database starts empty
+    let other =
+        DBMap::<u32, i64>::open_in_memory(None, Some("counters")).expect("Failed to open storage");
+    assert!(other.is_empty());
+}
+
+#[test]
+fn test_ttl_injected_clock() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let db =
+        DBMap::<i32, (u64, String)>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let now_secs = std::sync::Arc::new(AtomicU64::new(1_000_000));
+    let clock_secs = now_secs.clone();
+    let ttl_db =
+        TtlDBMap::new(db, Duration::from_secs(60)).with_clock(std::sync::Arc::new(move || {
+            UNIX_EPOCH + Duration::from_secs(clock_secs.load(Ordering::SeqCst))
+        }));
+    ttl_db
+        .insert(&1, &"1".to_string())
+        .expect("Failed to insert");
+
+    // the entry lives until the injected clock moves past its time to live
+    now_secs.fetch_add(59, Ordering::SeqCst);
+    assert!(ttl_db.contains_key(&1).expect("Failed to get"));
+    now_secs.fetch_add(1, Ordering::SeqCst);
+    assert!(!ttl_db.contains_key(&1).expect("Failed to get"));
+    assert_eq!(ttl_db.purge_expired().expect("Failed to purge"), 1);
+
+    // the clock is far behind the actual time, which is not used
+    assert!(UNIX_EPOCH + Duration::from_secs(now_secs.load(Ordering::SeqCst)) < SystemTime::now());
+}
+
+#[test]
+fn test_validate() {
+    let db = DBMap::<i32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..10).map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+
+    let report = db.validate().expect("Failed to validate");
+    assert_eq!(
+        report,
+        ValidationReport {
+            ok: 10,
+            corrupt: 0,
+            out_of_order: 0
+        }
+    );
+    assert!(report.is_healthy());
+
+    // a value too short to hold the length of a string
+    let key_buf = be_fix_int_ser(&5).expect("Failed to serialize key");
+    db.rocksdb
+        .put_cf(&db.cf(), &key_buf, [0xff])
+        .expect("Failed to insert");
+
+    // the corrupt entry is reported, and the scan carries on past it
+    let report = db.validate().expect("Failed to validate");
+    assert_eq!(
+        report,
+        ValidationReport {
+            ok: 9,
+            corrupt: 1,
+            out_of_order: 0
+        }
+    );
+    assert!(!report.is_healthy());
+}
+
+#[test]
+fn test_indexed_map() {
+    let rocks =
+        open_cf(temp_dir(), None, &["users", "users_by_email"]).expect("Failed to open storage");
+    let (users, by_email) =
+        reopen!(&rocks, "users";<u32, (String, String)>, "users_by_email";<String, u32>);
+    let users = users.with_index(by_email, |_id, (_name, email): &(String, String)| {
+        email.clone()
+    });
+
+    let alice = ("alice".to_string(), "alice@example.com".to_string());
+    users.insert(&1, &alice).expect("Failed to insert");
+    assert_eq!(
+        users
+            .get_by_index(&"alice@example.com".to_string())
+            .expect("Failed to get"),
+        Some((1, alice))
+    );
+
+    // changing the indexed field moves the index entry
+    let moved = ("alice".to_string(), "alice@example.org".to_string());
+    users.insert(&1, &moved).expect("Failed to insert");
+    assert_eq!(
+        users
+            .get_by_index(&"alice@example.com".to_string())
+            .expect("Failed to get"),
+        None
+    );
+    assert_eq!(
+        users
+            .index()
+            .get(&"alice@example.org".to_string())
+            .expect("Failed to get"),
+        Some(1)
+    );
+
+    // removing the entry clears the index
+    users.remove(&1).expect("Failed to remove");
+    assert_eq!(users.get(&1).expect("Failed to get"), None);
+    assert!(users.index().is_empty());
+}
+
+#[test]
+fn test_indexed_map_shared_index_key() {
+    let rocks =
+        open_cf(temp_dir(), None, &["users", "users_by_email"]).expect("Failed to open storage");
+    let (users, by_email) =
+        reopen!(&rocks, "users";<u32, (String, String)>, "users_by_email";<String, u32>);
+    let users = users.with_index(by_email, |_id, (_name, email): &(String, String)| {
+        email.clone()
+    });
+    let email = "shared@example.com".to_string();
+
+    // the index points to the last entry written under the shared index key
+    let alice = ("alice".to_string(), email.clone());
+    let bob = ("bob".to_string(), email.clone());
+    users.insert(&1, &alice).expect("Failed to insert");
+    users.insert(&2, &bob).expect("Failed to insert");
+    assert_eq!(
+        users.get_by_index(&email).expect("Failed to get"),
+        Some((2, bob.clone()))
+    );
+
+    // updating the entry it no longer points to leaves the index entry alone
+    let moved = ("alice".to_string(), "alice@example.org".to_string());
+    users.insert(&1, &moved).expect("Failed to insert");
+    assert_eq!(
+        users.get_by_index(&email).expect("Failed to get"),
+        Some((2, bob.clone()))
+    );
+
+    // and so does removing it
+    users.insert(&1, &alice).expect("Failed to insert");
+    users.insert(&2, &bob).expect("Failed to insert");
+    users.remove(&1).expect("Failed to remove");
+    assert_eq!(
+        users.get_by_index(&email).expect("Failed to get"),
+        Some((2, bob))
+    );
+}
+
+#[test]
+fn test_memtable_size() {
+    let db = DBMap::<i32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..1000).map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+
+    let size = db.memtable_size().expect("Failed to get memtable size");
+    assert!(size > 0);
+
+    // the flush empties the active memtable
+    db.flush().expect("Failed to flush");
+    assert!(db.memtable_size().expect("Failed to get memtable size") < size);
+}
+
+#[test]
+fn test_timestamp_keys() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let db =
+        DBMap::<Timestamp, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+    // inserted out of order, and across a byte boundary of the little-endian encoding
+    for secs in [300, 1, 256, 255, 70_000] {
+        db.insert(&Timestamp::from_systemtime(at(secs)), &secs.to_string())
+            .expect("Failed to insert");
+    }
+
+    let events: Vec<_> = db
+        .range_iter(&at(2).into(), &at(70_000).into())
+        .expect("Failed to get range")
+        .map(|(time, event)| (time.to_systemtime(), event))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (at(255), "255".to_string()),
+            (at(256), "256".to_string()),
+            (at(300), "300".to_string())
+        ]
+    );
+
+    // the times before the epoch saturate to it
+    assert_eq!(
+        Timestamp::from_systemtime(UNIX_EPOCH - Duration::from_secs(1)),
+        Timestamp::from_nanos(0)
+    );
+}
+
+#[test]
+fn test_reopen_with_options() {
+    let path = temp_dir();
+    let sst_files = |db: &DBMap<u32, String>| -> Vec<(String, usize)> {
+        db.rocksdb
+            .live_files()
+            .expect("Failed to list files")
+            .into_iter()
+            .map(|file| (file.name, file.size))
+            .collect()
+    };
+    let value = "a".repeat(1000);
+
+    let mut options = rocksdb::Options::default();
+    options.set_compression_type(rocksdb::DBCompressionType::None);
+    let db = DBMap::open(&path, Some(options), Some("table")).expect("Failed to open storage");
+    db.multi_insert((0..1000).map(|i| (i, value.clone())))
+        .expect("Failed to insert");
+    db.flush().expect("Failed to flush");
+    let old_files = sst_files(&db);
+    drop(db);
+
+    let mut options = rocksdb::Options::default();
+    options.set_compression_type(rocksdb::DBCompressionType::Zstd);
+    let db = DBMap::<u32, String>::reopen_with_options(&path, Some("table"), options)
+        .expect("Failed to reopen storage");
+
+    // the data written with the former options is still readable
+    assert_eq!(db.get(&999).expect("Failed to get"), Some(value.clone()));
+
+    // while the new writes are compressed
+    db.multi_insert((1000..2000).map(|i| (i, value.clone())))
+        .expect("Failed to insert");
+    db.flush().expect("Failed to flush");
+    let new_files: Vec<_> = sst_files(&db)
+        .into_iter()
+        .filter(|file| !old_files.contains(file))
+        .collect();
+    assert_eq!(new_files.len(), 1);
+    assert!(new_files[0].1 * 10 < old_files[0].1);
+}
+
+#[test]
+fn test_atomic_flush() {
+    let path = temp_dir();
+    let options: rocksdb::Options = DBMapOptions::new().atomic_flush(true).into();
+    let rocks = open_cf(&path, Some(options.clone()), &["First_CF", "Second_CF"])
+        .expect("Failed to open storage");
+    let (db_1, db_2) = reopen!(&rocks, "First_CF";<u32, String>, "Second_CF";<u32, String>);
+
+    DBBatch::new(&rocks)
+        .insert_batch(&db_1, (0..10).map(|i| (i, i.to_string())))
+        .expect("Failed to batch insert")
+        .insert_batch(&db_2, (0..10).map(|i| (i, (i * 2).to_string())))
+        .expect("Failed to batch insert")
+        .write()
+        .expect("Failed to write batch");
+    db_1.flush().expect("Failed to flush");
+    drop((db_1, db_2, rocks));
+
+    let rocks =
+        open_cf(&path, Some(options), &["First_CF", "Second_CF"]).expect("Failed to open storage");
+    let (db_1, db_2) = reopen!(&rocks, "First_CF";<u32, String>, "Second_CF";<u32, String>);
+    assert_eq!(db_1.get(&9).expect("Failed to get"), Some("9".to_string()));
+    assert_eq!(db_2.get(&9).expect("Failed to get"), Some("18".to_string()));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum JobState {
+    Pending,
+    Running,
+    Done,
+}
+
+impl KeyDiscriminant for JobState {
+    fn discriminant(&self) -> u8 {
+        match self {
+            JobState::Pending => 1,
+            JobState::Running => 2,
+            JobState::Done => 3,
+        }
+    }
+
+    fn from_discriminant(discriminant: u8) -> Option<Self> {
+        match discriminant {
+            1 => Some(JobState::Pending),
+            2 => Some(JobState::Running),
+            3 => Some(JobState::Done),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_enum_key_partitions() {
+    let db = DBMap::<CompositeKey<EnumKey<JobState>, u64>, String>::open(temp_dir(), None, None)
+        .expect("Failed to open storage");
+    let jobs = [
+        (JobState::Done, 1),
+        (JobState::Pending, 3),
+        (JobState::Running, 2),
+        (JobState::Pending, 256),
+        (JobState::Done, 4),
+    ];
+    for (state, id) in jobs {
+        db.insert(&CompositeKey::new(EnumKey(state), id), &id.to_string())
+            .expect("Failed to insert");
+    }
+
+    // the variant is stored as its discriminant byte
+    assert_eq!(
+        be_fix_int_ser(&EnumKey(JobState::Running)).expect("Failed to serialize"),
+        vec![2]
+    );
+
+    let pending: Vec<_> = db
+        .prefix_iter(&CompositeKey::<EnumKey<JobState>, u64>::prefix(EnumKey(
+            JobState::Pending,
+        )))
+        .expect("Prefix iteration failed")
+        .map(|(key, _)| (key.first.0, key.second))
+        .collect();
+    assert_eq!(
+        pending,
+        vec![(JobState::Pending, 3), (JobState::Pending, 256)]
+    );
+
+    // the variants sort by discriminant
+    let states: Vec<_> = db.keys().map(|key| key.first.0).collect();
+    assert_eq!(
+        states,
+        vec![
+            JobState::Pending,
+            JobState::Pending,
+            JobState::Running,
+            JobState::Done,
+            JobState::Done
+        ]
+    );
+}
+
+#[test]
+fn test_multi_insert_overwrite() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..5).map(|i| (i, "old".to_string())))
+        .expect("Failed to insert");
+
+    let written = db
+        .multi_insert_with_policy(
+            (3..8).map(|i| (i, "new".to_string())),
+            InsertPolicy::Overwrite,
+        )
+        .expect("Failed to insert");
+    assert_eq!(written, 5);
+    assert_eq!(db.get(&2).expect("Failed to get"), Some("old".to_string()));
+    assert_eq!(db.get(&3).expect("Failed to get"), Some("new".to_string()));
+    assert_eq!(db.get(&7).expect("Failed to get"), Some("new".to_string()));
+}
+
+#[test]
+fn test_multi_insert_skip_existing() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..5).map(|i| (i, "old".to_string())))
+        .expect("Failed to insert");
+
+    let written = db
+        .multi_insert_with_policy(
+            (3..8).map(|i| (i, "new".to_string())),
+            InsertPolicy::SkipExisting,
+        )
+        .expect("Failed to insert");
+    assert_eq!(written, 3);
+    assert_eq!(db.get(&3).expect("Failed to get"), Some("old".to_string()));
+    assert_eq!(db.get(&4).expect("Failed to get"), Some("old".to_string()));
+    assert_eq!(db.get(&5).expect("Failed to get"), Some("new".to_string()));
+    assert_eq!(db.get(&7).expect("Failed to get"), Some("new".to_string()));
+}
+
+#[test]
+fn test_multi_insert_fail_if_exists() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..5).map(|i| (i, "old".to_string())))
+        .expect("Failed to insert");
+
+    let result = db.multi_insert_with_policy(
+        (3..8).map(|i| (i, "new".to_string())),
+        InsertPolicy::FailIfExists,
+    );
+    assert_eq!(result, Err(TypedStoreError::KeyExists));
+    // nothing was written, not even the new keys
+    assert_eq!(db.get(&3).expect("Failed to get"), Some("old".to_string()));
+    assert_eq!(db.get(&5).expect("Failed to get"), None);
+
+    let written = db
+        .multi_insert_with_policy(
+            (5..8).map(|i| (i, "new".to_string())),
+            InsertPolicy::FailIfExists,
+        )
+        .expect("Failed to insert");
+    assert_eq!(written, 3);
+}
+
+#[test]
+fn test_clone_shares_database() {
+    // neither the keys nor the values are Clone
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Record(String);
+
+    let path = temp_dir();
+    let db = DBMap::<Record, Record>::open(&path, None, None).expect("Failed to open storage");
+    let clone = db.clone();
+    assert!(Arc::ptr_eq(&db.rocksdb, &clone.rocksdb));
+
+    let handle = std::thread::spawn(move || {
+        clone
+            .insert(&Record("key".to_string()), &Record("value".to_string()))
+            .expect("Failed to insert")
+    });
+    handle.join().expect("Writing thread panicked");
+
+    assert_eq!(
+        db.get(&Record("key".to_string())).expect("Failed to get"),
+        Some(Record("value".to_string()))
+    );
+    // the database can't be opened again while a clone is alive
+    assert!(DBMap::<Record, Record>::open(&path, None, None).is_err());
+}
+
+#[test]
+fn test_prefix_count() {
+    let db = DBMap::open(temp_dir(), None, None).expect("Failed to open storage");
+    for (group, size) in [(1u32, 7u32), (2, 3), (256, 12)] {
+        for i in 0..size {
+            db.insert(&(group, i), &i.to_string())
+                .expect("Failed to insert");
+        }
+    }
+
+    assert_eq!(db.prefix_count(&1u32).expect("Failed to count"), 7);
+    assert_eq!(db.prefix_count(&2u32).expect("Failed to count"), 3);
+    assert_eq!(db.prefix_count(&256u32).expect("Failed to count"), 12);
+    assert_eq!(db.prefix_count(&3u32).expect("Failed to count"), 0);
+}
+
+#[test]
+fn test_verify_checksum() {
+    let db = DBMap::<u32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.multi_insert((0..1000).map(|i| (i, i.to_string())))
+        .expect("Failed to insert");
+    // the blocks to verify are those of the SST files
+    db.rocksdb.flush_cf(&db.cf()).expect("Failed to flush");
+    db.insert(&1000, &"unflushed".to_string())
+        .expect("Failed to insert");
+
+    assert_eq!(db.verify_checksum(), Ok(()));
+    assert_eq!(db.get(&500), Ok(Some("500".to_string())));
+
+    // the reads verify the checksums of the blocks they load, unless told not to
+    let unverified = db.clone().with_verify_checksums(false);
+    assert_eq!(unverified.get(&500), Ok(Some("500".to_string())));
+    let verified = db.with_verify_checksums(true);
+    assert_eq!(verified.iter().count(), 1001);
+    assert_eq!(
+        verified.multi_get([&1, &1000]),
+        Ok(vec![Some("1".to_string()), Some("unflushed".to_string())])
+    );
+}
+
+#[test]
+fn test_validate_key_ordering() {
+    let db =
+        DBMap::<(u32, u64), String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.validate_key_ordering(&[(2, 0), (0, 256), (0, 1), (1, u64::MAX)])
+        .expect("Tuples of unsigned integers preserve the ordering");
+
+    // negative integers serialize, in two's complement, after the positive ones
+    let db = DBMap::<i32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    assert_eq!(
+        db.validate_key_ordering(&[1, -1, 0]),
+        Err(TypedStoreError::KeyOrdering(
+            "-1 is stored after 0".to_string()
+        ))
+    );
+
+    // strings are stored after their length
+    let db = DBMap::<String, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    assert!(matches!(
+        db.validate_key_ordering(&["b".to_string(), "aa".to_string()]),
+        Err(TypedStoreError::KeyOrdering(_))
+    ));
+
+    // the keys stored are checked as well, without any probe
+    let db = DBMap::<i64, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    db.validate_key_ordering(&[])
+        .expect("An empty map has no key out of order");
+    for key in [-2, 3] {
+        db.insert(&key, &key.to_string()).expect("Failed to insert");
+    }
+    assert_eq!(
+        db.validate_key_ordering(&[]),
+        Err(TypedStoreError::KeyOrdering(
+            "-2 is stored after 3".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_get_at_sequence() {
+    let db = DBMap::<u32, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let mut history = db.history(2);
+
+    db.insert(&1, &"v1".to_string()).expect("Failed to insert");
+    let sequence = history.capture();
+    db.insert(&1, &"v2".to_string()).expect("Failed to insert");
+    db.insert(&2, &"v1".to_string()).expect("Failed to insert");
+
+    assert_eq!(db.get(&1).unwrap(), Some("v2".to_string()));
+    assert_eq!(
+        history.get_at_sequence(&1, sequence),
+        Ok(Some("v1".to_string()))
+    );
+    assert_eq!(history.get_at_sequence(&2, sequence), Ok(None));
+    assert_eq!(
+        history.get_at_sequence(&1, sequence + 1),
+        Err(TypedStoreError::SequenceNotRetained(sequence + 1))
+    );
+
+    // the oldest states are released beyond the capacity of the history
+    let latest = history.capture();
+    assert_eq!(latest, db.latest_sequence_number());
+    db.insert(&1, &"v3".to_string()).expect("Failed to insert");
+    let last = history.capture();
+    assert_eq!(history.sequences().collect::<Vec<_>>(), vec![latest, last]);
+    assert!(history.get_at_sequence(&1, sequence).is_err());
+}
+
+#[test]
+fn test_migrate_into() {
+    let rocks = open_cf(temp_dir(), None, &["users_v1", "users_v2"]).unwrap();
+    let source =
+        DBMap::<u32, String>::reopen(&rocks, Some("users_v1")).expect("Failed to open storage");
+    let target = DBMap::<(String, u32), usize>::reopen(&rocks, Some("users_v2"))
+        .expect("Failed to open storage");
+    source
+        .multi_insert([
+            (1, "alice".to_string()),
+            (2, "bob".to_string()),
+            (3, String::new()),
+        ])
+        .expect("Failed to insert");
+
+    // rekey by name, skipping the nameless entries
+    let report = source
+        .migrate_into(&target, |id, name| {
+            (!name.is_empty()).then(|| ((name.clone(), id), name.len()))
+        })
+        .expect("Failed to migrate");
+
+    assert_eq!(
+        report,
+        MigrationReport {
+            migrated: 2,
+            skipped: 1,
+            cancelled: false,
+        }
+    );
+    // the names are serialized after their length, which they sort by first
+    assert_eq!(
+        target.iter().collect::<Vec<_>>(),
+        vec![(("bob".to_string(), 2), 3), (("alice".to_string(), 1), 5)]
+    );
+    // the source is left untouched
+    assert_eq!(source.iter().count(), 3);
+}
+
+#[test]
+fn test_migrate_into_cancelled() {
+    let rocks = open_cf(temp_dir(), None, &["source", "target"]).unwrap();
+    let source = DBMap::<u32, u32>::reopen(&rocks, Some("source")).expect("Failed to open storage");
+    let target = DBMap::<u32, u32>::reopen(&rocks, Some("target")).expect("Failed to open storage");
+    source
+        .multi_insert((0..5000).map(|i| (i, i)))
+        .expect("Failed to insert");
+
+    // cancel from the progress report of the first batch
+    let cancel = CancellationToken::new();
+    let reported = std::cell::RefCell::new(Vec::new());
+    let progress = |processed: u64, total_estimate: u64| {
+        reported.borrow_mut().push(processed);
+        assert!(total_estimate > 0);
+        cancel.cancel();
+    };
+    let report = source
+        .migrate_into_with(&target, |k, v| Some((k, v)), &cancel, Some(&progress))
+        .expect("Failed to migrate");
+
+    // the migration stops right after the first batch, which is written
+    assert_eq!(
+        report,
+        MigrationReport {
+            migrated: 1000,
+            skipped: 0,
+            cancelled: true,
+        }
+    );
+    assert_eq!(reported.into_inner(), vec![1000]);
+    assert_eq!(target.iter().count(), 1000);
+    assert_eq!(target.iter().last(), Some((999, 999)));
+}
+
+#[test]
+fn test_secondary_get_stale() {
+    let path = temp_dir();
+    let primary =
+        DBMap::<u32, String>::open(&path, None, Some("table")).expect("Failed to open storage");
+    primary
+        .insert(&1, &"old".to_string())
+        .expect("Failed to insert");
+
+    let secondary = SecondaryDBMap::<u32, String>::open(path, temp_dir(), None, Some("table"))
+        .expect("Failed to open the secondary");
+    assert_eq!(secondary.get_stale(&1), Ok(Some("old".to_string())));
+
+    // the writes of the primary are only observed once caught up
+    primary
+        .insert(&1, &"new".to_string())
+        .expect("Failed to insert");
+    assert_eq!(secondary.get_stale(&1), Ok(Some("old".to_string())));
+    secondary
+        .catch_up_with_primary()
+        .expect("Failed to catch up with the primary");
+    assert_eq!(secondary.get_stale(&1), Ok(Some("new".to_string())));
+}
+
+#[test]
+fn test_concurrent_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<DBMap<u64, String>>();
+
+    let db = DBMap::<u64, String>::open(temp_dir(), None, None).expect("Failed to open storage");
+
+    // each thread writes and reads back a range of keys of its own
+    let handles: Vec<_> = (0..4u64)
+        .map(|thread| {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                let keys = thread * 100..(thread + 1) * 100;
+                for key in keys.clone() {
+                    db.insert(&key, &key.to_string()).expect("Failed to insert");
+                }
+                db.multi_remove(keys.clone().filter(|key| key % 2 == 1))
+                    .expect("Failed to remove");
+                for key in keys {
+                    let expected = (key % 2 == 0).then(|| key.to_string());
+                    assert_eq!(db.get(&key).expect("Failed to get"), expected);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("Failed to join the thread");
+    }
+
+    assert_eq!(db.iter().count(), 200);
+    let range: Vec<_> = db
+        .range_iter(&98, &104)
+        .expect("Failed to scan")
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(range, vec![98, 100, 102]);
+}
+
+#[test]
+fn test_watch_events() {
+    let db = DBMap::<u32, String>::open(temp_dir(), None, Some("table"))
+        .expect("Failed to open storage");
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let watcher = db
+        .watch_events(std::time::Duration::from_millis(10), move |event| {
+            let _ = sender.send(event);
+        })
+        .expect("Failed to watch the events");
+
+    db.insert(&1, &"1".to_string()).expect("Failed to insert");
+    db.flush().expect("Failed to flush");
+
+    let event = receiver
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("No event was reported");
+    assert!(matches!(event, TableEvent::Flush { files: 1, bytes } if bytes > 0));
+
+    // the callback is released with the watcher
+    drop(watcher);
+    assert!(receiver.recv().is_err());
+}
+
+#[test]
+fn test_delete_prefix() {
+    let db =
+        DBMap::<(u8, u8), String>::open(temp_dir(), None, None).expect("Failed to open storage");
+    for first in [0u8, 1, 2, 254, 255] {
+        db.multi_insert((0..=255).map(|second| ((first, second), format!("{first}-{second}"))))
+            .expect("Failed to insert");
+    }
+
+    // a prefix bounded by the next one
+    assert_eq!(db.delete_prefix(&1u8), Ok(256));
+    // a prefix of 0xFF bytes, with no upper bound
+    assert_eq!(db.delete_prefix(&255u8), Ok(256));
+
+    let firsts: std::collections::BTreeSet<_> = db.keys().map(|(first, _)| first).collect();
+    assert_eq!(firsts.into_iter().collect::<Vec<_>>(), vec![0, 2, 254]);
+    assert_eq!(db.iter().count(), 3 * 256);
+    assert_eq!(db.delete_prefix(&1u8), Ok(0));
+}
+
+#[test]
+fn test_multi_snapshot() {
+    let rocks = open_cf(temp_dir(), None, &["accounts", "balances"]).unwrap();
+    let accounts =
+        DBMap::<u32, String>::reopen(&rocks, Some("accounts")).expect("Failed to open storage");
+    let balances =
+        DBMap::<u32, u64>::reopen(&rocks, Some("balances")).expect("Failed to open storage");
+    accounts
+        .insert(&1, &"alice".to_string())
+        .expect("Failed to insert");
+    balances.insert(&1, &100).expect("Failed to insert");
+
+    let snapshot = MultiSnapshot::new(&rocks);
+    accounts.remove(&1).expect("Failed to remove");
+    accounts
+        .insert(&2, &"bob".to_string())
+        .expect("Failed to insert");
+    balances.insert(&1, &0).expect("Failed to insert");
+
+    // the snapshot reads both tables as of before the mutations
+    assert_eq!(snapshot.get(&accounts, &1), Ok(Some("alice".to_string())));
+    assert_eq!(snapshot.contains_key(&accounts, &2), Ok(false));
+    assert_eq!(snapshot.get(&balances, &1), Ok(Some(100)));
+    assert_eq!(balances.get(&1), Ok(Some(0)));
+    // a map's snapshot covers the other maps of its database
+    assert_eq!(
+        balances.snapshot().get(&accounts, &2),
+        Ok(Some("bob".to_string()))
+    );
+
+    let other = DBMap::<u32, u64>::open(temp_dir(), None, None).expect("Failed to open storage");
+    assert_eq!(
+        snapshot.get(&other, &1),
+        Err(TypedStoreError::CrossDBSnapshot)
+    );
+}
+
+#[test]
+fn test_level_stats() {
+    let db = DBMap::<u32, String>::open(temp_dir(), None, Some("table"))
+        .expect("Failed to open storage");
+    assert_eq!(db.level_stats(), Ok(vec![]));
+
+    // stay below the level-0 compaction trigger, for the files to stay put
+    for batch in 0..2u32 {
+        db.multi_insert((0..100).map(|i| (batch * 100 + i, i.to_string())))
+            .expect("Failed to insert");
+        db.flush().expect("Failed to flush");
+    }
+    db.remove(&0).expect("Failed to remove");
+    db.flush().expect("Failed to flush");
+
+    let stats = db.level_stats().expect("Failed to read the level stats");
+    assert!(!stats.is_empty());
+    assert!(stats.iter().all(|stat| stat.files > 0 && stat.bytes > 0));
+    assert_eq!(stats.iter().map(|stat| stat.files).sum::<usize>(), 3);
+    assert_eq!(stats.iter().map(|stat| stat.entries).sum::<u64>(), 201);
+    assert_eq!(stats.iter().map(|stat| stat.deletions).sum::<u64>(), 1);
+}
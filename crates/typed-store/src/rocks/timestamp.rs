@@ -0,0 +1,46 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// A point in time usable as a key, stored as a big-endian `u64` of nanoseconds since
+/// the unix epoch: the keys of a `DBMap` keyed by `Timestamp` sort chronologically, and
+/// the events between two times are scanned with `DBMap::range_iter`.
+///
+/// Distinct events may happen at the same time, in which case they are better keyed by
+/// a `CompositeKey<Timestamp, _>` disambiguating them.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub fn from_nanos(nanos: u64) -> Self {
+        Timestamp(nanos)
+    }
+
+    /// Converts a system time, saturating the times before the unix epoch to the epoch,
+    /// and the times after the year 2554 to the largest timestamp.
+    pub fn from_systemtime(time: SystemTime) -> Self {
+        let nanos = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_nanos());
+        Timestamp(nanos.try_into().unwrap_or(u64::MAX))
+    }
+
+    pub fn to_systemtime(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.0)
+    }
+
+    /// The time, in nanoseconds since the unix epoch.
+    pub fn as_nanos(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Timestamp::from_systemtime(time)
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rocksdb::WriteBatch;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{be_fix_int_ser, DBMap, TypedStoreError};
+use crate::traits::Map;
+
+/// A map whose entries expire individually.
+///
+/// Each value is stored along with its expiry time, as a `(expiry, value)` pair where
+/// the expiry is expressed in milliseconds since the unix epoch. Expired entries read
+/// as absent. Their space is reclaimed lazily: on access if `evict_on_read` is set,
+/// and otherwise by `purge_expired`.
+///
+/// An expired entry is only deleted if it is still expired once the writes of the map
+/// are locked out, so that an entry written in the meantime is kept. Writes to the
+/// underlying map that bypass this `TtlDBMap` are not locked out, and may be deleted.
+pub struct TtlDBMap<K, V> {
+    map: DBMap<K, (u64, V)>,
+    ttl: Duration,
+    evict_on_read: bool,
+    clock: Arc<dyn Fn() -> SystemTime + Send + Sync>,
+    /// held by the writes, and by the deletions of the expired entries
+    write_lock: Mutex<()>,
+}
+
+impl<K, V> TtlDBMap<K, V> {
+    /// Wraps a map, using `ttl` as the time to live of the entries inserted with `insert`.
+    pub fn new(map: DBMap<K, (u64, V)>, ttl: Duration) -> Self {
+        TtlDBMap {
+            map,
+            ttl,
+            evict_on_read: false,
+            clock: Arc::new(SystemTime::now),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// When set, reading an expired entry also deletes it.
+    pub fn evict_on_read(mut self, evict_on_read: bool) -> Self {
+        self.evict_on_read = evict_on_read;
+        self
+    }
+
+    /// Replaces the clock telling the current time, `SystemTime::now` by default, against
+    /// which the entries expire. Tests can then advance time without sleeping.
+    pub fn with_clock(mut self, clock: Arc<dyn Fn() -> SystemTime + Send + Sync>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn now_millis(&self) -> u64 {
+        (self.clock)()
+            .duration_since(UNIX_EPOCH)
+            .expect("Current time is before the unix epoch")
+            .as_millis() as u64
+    }
+
+    fn lock_writes(&self) -> MutexGuard<'_, ()> {
+        // the lock guards no data, which a panic could have left inconsistent
+        self.write_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K, V> TtlDBMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Inserts the key-value pair, expiring after the map's default time to live.
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), TypedStoreError> {
+        self.insert_with_ttl(key, value, self.ttl)
+    }
+
+    /// Inserts the key-value pair, expiring after the given time to live.
+    pub fn insert_with_ttl(
+        &self,
+        key: &K,
+        value: &V,
+        ttl: Duration,
+    ) -> Result<(), TypedStoreError> {
+        let expiry = self.now_millis().saturating_add(ttl.as_millis() as u64);
+        let key_buf = be_fix_int_ser(key)?;
+        // (u64, &V) serializes exactly as the stored (u64, V)
+        let value_buf = bincode::serialize(&(expiry, value))?;
+
+        let _guard = self.lock_writes();
+        self.map
+            .rocksdb
+            .put_cf(&self.map.cf(), &key_buf, &value_buf)?;
+        Ok(())
+    }
+
+    /// Returns the value for the given key, if it exists and has not expired.
+    pub fn get(&self, key: &K) -> Result<Option<V>, TypedStoreError> {
+        match self.map.get(key)? {
+            Some((expiry, value)) if expiry > self.now_millis() => Ok(Some(value)),
+            Some(_) => {
+                if self.evict_on_read {
+                    self.remove_if_expired(key)?;
+                }
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Deletes the entry for the given key if it is expired, as checked with the writes
+    /// locked out.
+    fn remove_if_expired(&self, key: &K) -> Result<(), TypedStoreError> {
+        let cf = self.map.cf();
+        let key_buf = be_fix_int_ser(key)?;
+        let _guard = self.lock_writes();
+        if let Some(value_buf) =
+            self.map
+                .rocksdb
+                .get_pinned_cf_opt(&cf, &key_buf, &self.map.read_options())?
+        {
+            let expiry: u64 = bincode::deserialize(&value_buf)?;
+            if expiry <= self.now_millis() {
+                self.map.rocksdb.delete_cf(&cf, &key_buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the map contains a value for the key that has not expired.
+    pub fn contains_key(&self, key: &K) -> Result<bool, TypedStoreError> {
+        self.get(key).map(|value| value.is_some())
+    }
+
+    /// Extends the time to live of the entry for the given key by the map's default
+    /// time to live, from now. Returns whether the key had an entry that had not expired.
+    pub fn touch(&self, key: &K) -> Result<bool, TypedStoreError> {
+        self.touch_with_ttl(key, self.ttl)
+    }
+
+    /// Extends the time to live of the entry for the given key to the given time to
+    /// live, from now. Returns whether the key had an entry that had not expired; an
+    /// expired entry is left as is.
+    ///
+    /// Only the expiry prefix of the stored value is rewritten, the value itself is
+    /// copied over as bytes and never deserialized.
+    pub fn touch_with_ttl(&self, key: &K, ttl: Duration) -> Result<bool, TypedStoreError> {
+        let now = self.now_millis();
+        let cf = self.map.cf();
+        let key_buf = be_fix_int_ser(key)?;
+        let _guard = self.lock_writes();
+        let mut value_buf =
+            match self
+                .map
+                .rocksdb
+                .get_pinned_cf_opt(&cf, &key_buf, &self.map.read_options())?
+            {
+                Some(data) => data.to_vec(),
+                None => return Ok(false),
+            };
+
+        let expiry: u64 = bincode::deserialize(&value_buf)?;
+        if expiry <= now {
+            return Ok(false);
+        }
+        let expiry = now.saturating_add(ttl.as_millis() as u64);
+        let expiry_buf = bincode::serialize(&expiry)?;
+        value_buf[..expiry_buf.len()].copy_from_slice(&expiry_buf);
+
+        self.map.rocksdb.put_cf(&cf, &key_buf, &value_buf)?;
+        Ok(true)
+    }
+
+    /// Removes the entry for the given key, whether it expired or not.
+    pub fn remove(&self, key: &K) -> Result<(), TypedStoreError> {
+        let _guard = self.lock_writes();
+        self.map.remove(key)
+    }
+
+    /// Deletes all the expired entries, returning how many were deleted.
+    ///
+    /// Only the expiry prefix of each stored value is decoded, the values themselves
+    /// are never deserialized.
+    pub fn purge_expired(&self) -> Result<usize, TypedStoreError> {
+        let now = self.now_millis();
+        let cf = self.map.cf();
+        let mut batch = WriteBatch::default();
+        let mut purged = 0;
+        let _guard = self.lock_writes();
+
+        let mut db_iter = self
+            .map
+            .rocksdb
+            .raw_iterator_cf_opt(&cf, self.map.read_options());
+        db_iter.seek_to_first();
+        while db_iter.valid() {
+            if let (Some(key), Some(value)) = (db_iter.key(), db_iter.value()) {
+                // the expiry is the leading element of the stored tuple
+                let expiry: u64 = bincode::deserialize(value)?;
+                if expiry <= now {
+                    batch.delete_cf(&cf, key);
+                    purged += 1;
+                }
+            }
+            db_iter.next();
+        }
+        db_iter.status()?;
+
+        self.map.rocksdb.write(batch)?;
+        Ok(purged)
+    }
+}
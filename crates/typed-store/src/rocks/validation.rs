@@ -0,0 +1,92 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use bincode::Options;
+use serde::de::DeserializeOwned;
+
+use super::{DBMap, TypedStoreError};
+
+/// The outcome of `DBMap::validate`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// the number of entries whose key and value both deserialize
+    pub ok: usize,
+    /// the number of entries whose key or value fails to deserialize
+    pub corrupt: usize,
+    /// the number of keys not strictly greater, byte-wise, than the key before them
+    pub out_of_order: usize,
+}
+
+impl ValidationReport {
+    /// Returns true if no entry of the table broke any invariant.
+    pub fn is_healthy(&self) -> bool {
+        self.corrupt == 0 && self.out_of_order == 0
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned> DBMap<K, V> {
+    /// Scans the whole column family, checking that every key and every value
+    /// deserializes, and that the keys are in strictly increasing byte order, e.g. to
+    /// assert that a table is sane after a repair or a migration.
+    ///
+    /// The scan carries on past the broken entries, which are only counted. Errors
+    /// are only returned when the database itself fails.
+    pub fn validate(&self) -> Result<ValidationReport, TypedStoreError> {
+        let config = bincode::DefaultOptions::new()
+            .with_big_endian()
+            .with_fixint_encoding();
+        let mut report = ValidationReport::default();
+        let mut previous_key: Option<Vec<u8>> = None;
+
+        let mut db_iter = self
+            .rocksdb
+            .raw_iterator_cf_opt(&self.cf(), self.read_options());
+        db_iter.seek_to_first();
+        while db_iter.valid() {
+            if let (Some(key), Some(value)) = (db_iter.key(), db_iter.value()) {
+                if matches!(&previous_key, Some(previous) if previous.as_slice() >= key) {
+                    report.out_of_order += 1;
+                }
+                let key_ok = config.deserialize::<K>(key).is_ok();
+                let value_ok = bincode::deserialize::<V>(value).is_ok();
+                if key_ok && value_ok {
+                    report.ok += 1;
+                } else {
+                    report.corrupt += 1;
+                }
+                previous_key = Some(key.to_vec());
+            }
+            db_iter.next();
+        }
+        db_iter.status()?;
+
+        Ok(report)
+    }
+}
+
+impl<K, V> DBMap<K, V> {
+    /// Reads every block of the column family, verifying its checksum, e.g. after
+    /// suspected disk issues. A corrupted block fails the scan with the error reported
+    /// by RocksDB, which names the file at fault.
+    ///
+    /// The reads of the map already verify the checksums of the blocks they load (see
+    /// `with_verify_checksums`), but they only catch the corruption of the data they
+    /// happen to read. The scan does not fill the block cache, so that it doesn't evict
+    /// the working set of the other reads. It is still served the blocks already in the
+    /// cache, whose copies on disk are then not verified: a corruption of these files is
+    /// only caught once their blocks are evicted, e.g. after reopening the database.
+    pub fn verify_checksum(&self) -> Result<(), TypedStoreError> {
+        let mut readopts = rocksdb::ReadOptions::default();
+        readopts.set_verify_checksums(true);
+        readopts.fill_cache(false);
+
+        let mut db_iter = self.rocksdb.raw_iterator_cf_opt(&self.cf(), readopts);
+        db_iter.seek_to_first();
+        while db_iter.valid() {
+            // loading the values loads the data blocks, whose checksums are checked
+            db_iter.value();
+            db_iter.next();
+        }
+        db_iter.status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::{DBBatch, DBMap, TypedStoreError};
+use std::sync::Arc;
+
+/// The prefix of the reserved keys of the default column family under which the versions
+/// of the tables are stored, followed by the name of the table's column family.
+const VERSION_KEY_PREFIX: &[u8] = b"typed_store/version/";
+
+impl<K, V> DBMap<K, V> {
+    /// The reserved key holding the version of this map. The versions are kept in the
+    /// default column family, out of the keyspace of the maps they track, which rules out
+    /// the maps of the default column family itself.
+    fn version_key(&self) -> Result<Vec<u8>, TypedStoreError> {
+        if self.cf == rocksdb::DEFAULT_COLUMN_FAMILY_NAME {
+            return Err(TypedStoreError::VersionNotTracked(
+                "the table is on the default column family".to_string(),
+            ));
+        }
+        let mut key = VERSION_KEY_PREFIX.to_vec();
+        key.extend_from_slice(self.cf.as_bytes());
+        Ok(key)
+    }
+
+    /// Reads the stored version of this map, 0 if it was never stored.
+    pub(crate) fn stored_version(&self) -> Result<u64, TypedStoreError> {
+        match self.rocksdb.get_pinned(self.version_key()?)? {
+            Some(data) => Ok(u64::from_be_bytes(data.as_ref().try_into().map_err(
+                |_| TypedStoreError::SerializationError("malformed table version".to_string()),
+            )?)),
+            None => Ok(0),
+        }
+    }
+
+    pub(crate) fn store_version(&self, version: u64) -> Result<(), TypedStoreError> {
+        self.rocksdb
+            .put(self.version_key()?, version.to_be_bytes())?;
+        Ok(())
+    }
+
+    /// Checks that the database serves reads, by reading the bare prefix of the reserved
+    /// keys, which is never written.
+    pub(crate) fn ping(&self) -> Result<(), TypedStoreError> {
+        self.rocksdb.get_pinned(VERSION_KEY_PREFIX)?;
+        Ok(())
+    }
+}
+
+impl DBBatch {
+    /// Stores the version of the map along with the other operations of the batch, so
+    /// that the version is updated atomically with the mutation it accounts for.
+    pub(crate) fn put_version<K, V>(
+        mut self,
+        db: &DBMap<K, V>,
+        version: u64,
+    ) -> Result<Self, TypedStoreError> {
+        if !Arc::ptr_eq(&db.rocksdb, &self.rocksdb) {
+            return Err(TypedStoreError::CrossDBBatch);
+        }
+        self.batch.put(db.version_key()?, version.to_be_bytes());
+        Ok(self)
+    }
+}
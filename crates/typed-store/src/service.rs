@@ -0,0 +1,81 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{Store, StoreError};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// An operation of a `Store`, sent through its `tower::Service` implementation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreRequest<K, V> {
+    Read(K),
+    ReadAll(Vec<K>),
+    Write(K, V),
+    WriteAll(Vec<(K, V)>),
+    Remove(K),
+    RemoveAll(Vec<K>),
+}
+
+/// The result of a `StoreRequest`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreResponse<V> {
+    /// the value read by a `Read`
+    Value(Option<V>),
+    /// the values read by a `ReadAll`, in the order of its keys
+    Values(Vec<Option<V>>),
+    /// the acknowledgement of a write or a removal, once it is applied
+    Done,
+}
+
+/// A `Store` is a `tower::Service` serving `StoreRequest`s, so that it can be wrapped
+/// in tower middleware (e.g. timeouts, rate limits or load shedding). The store is
+/// always ready: its calls wait on the queue of the background task instead, as the
+/// corresponding methods do.
+///
+/// Unlike `Store::write` and `Store::remove`, a `Write` or a `Remove` is acknowledged
+/// once applied, as a `write_all` or a `remove_all` of a single key, so that the
+/// middleware (e.g. a timeout) observes its outcome.
+impl<Key, Value> tower::Service<StoreRequest<Key, Value>> for Store<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send + Sync + 'static,
+    Value: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    type Response = StoreResponse<Value>;
+    type Error = StoreError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: StoreRequest<Key, Value>) -> Self::Future {
+        let store = self.clone();
+        Box::pin(async move {
+            match request {
+                StoreRequest::Read(key) => store.read(key).await.map(StoreResponse::Value),
+                StoreRequest::ReadAll(keys) => {
+                    store.read_all(keys).await.map(StoreResponse::Values)
+                }
+                StoreRequest::Write(key, value) => {
+                    store.write_all(vec![(key, value)]).await?;
+                    Ok(StoreResponse::Done)
+                }
+                StoreRequest::WriteAll(key_values) => {
+                    store.write_all(key_values).await?;
+                    Ok(StoreResponse::Done)
+                }
+                StoreRequest::Remove(key) => {
+                    store.remove_all(vec![key]).await?;
+                    Ok(StoreResponse::Done)
+                }
+                StoreRequest::RemoveAll(keys) => {
+                    store.remove_all(keys).await?;
+                    Ok(StoreResponse::Done)
+                }
+            }
+        })
+    }
+}
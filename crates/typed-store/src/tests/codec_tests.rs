@@ -0,0 +1,157 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{
+    codec::{DynCodec, DynValue, VersionedCodec, VersionedValue},
+    rocks::DBMap,
+    Map,
+};
+use serde::{Deserialize, Serialize};
+
+fn temp_dir() -> std::path::PathBuf {
+    tempfile::tempdir()
+        .expect("Failed to open temporary directory")
+        .into_path()
+}
+
+trait Shape {
+    fn area(&self) -> u64;
+}
+
+#[derive(Serialize, Deserialize)]
+struct Square {
+    side: u64,
+}
+
+impl Shape for Square {
+    fn area(&self) -> u64 {
+        self.side * self.side
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Rectangle {
+    width: u64,
+    height: u64,
+}
+
+impl Shape for Rectangle {
+    fn area(&self) -> u64 {
+        self.width * self.height
+    }
+}
+
+fn shape_codec() -> DynCodec<dyn Shape> {
+    DynCodec::new()
+        .register("square", |s: Square| -> Box<dyn Shape> { Box::new(s) })
+        .register("rectangle", |r: Rectangle| -> Box<dyn Shape> {
+            Box::new(r)
+        })
+}
+
+#[test]
+fn dyn_codec_round_trip() {
+    let db = DBMap::<u32, DynValue>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let codec = shape_codec();
+
+    codec
+        .insert(&db, &1, "square", &Square { side: 3 })
+        .expect("Failed to insert");
+    codec
+        .insert(
+            &db,
+            &2,
+            "rectangle",
+            &Rectangle {
+                width: 2,
+                height: 5,
+            },
+        )
+        .expect("Failed to insert");
+
+    let square = codec.get(&db, &1).expect("Failed to get").unwrap();
+    assert_eq!(square.area(), 9);
+    let rectangle = codec.get(&db, &2).expect("Failed to get").unwrap();
+    assert_eq!(rectangle.area(), 10);
+    assert!(codec.get(&db, &3).expect("Failed to get").is_none());
+
+    // The raw values carry the tag of their concrete type
+    assert_eq!(db.get(&1).unwrap().unwrap().tag(), "square");
+    assert_eq!(db.get(&2).unwrap().unwrap().tag(), "rectangle");
+}
+
+#[test]
+fn dyn_codec_unregistered_tag() {
+    let codec = shape_codec();
+    assert!(codec.encode("circle", &Square { side: 1 }).is_err());
+
+    // A value whose tag is unknown to the reader fails to decode
+    let value = codec.encode("square", &Square { side: 1 }).unwrap();
+    let reader = DynCodec::<dyn Shape>::new()
+        .register("rectangle", |r: Rectangle| -> Box<dyn Shape> {
+            Box::new(r)
+        });
+    assert!(reader.decode(&value).is_err());
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct JobV1 {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct JobV2 {
+    name: String,
+    retries: u32,
+}
+
+#[test]
+fn versioned_codec_reads_old_versions() {
+    let db =
+        DBMap::<u32, VersionedValue>::open(temp_dir(), None, None).expect("Failed to open storage");
+    let v1 = VersionedCodec::<JobV1>::new(1).register(1, |job: JobV1| job);
+    v1.insert(
+        &db,
+        &1,
+        &JobV1 {
+            name: "compact".to_string(),
+        },
+    )
+    .expect("Failed to insert");
+
+    // The schema changes: the v2 reader keeps the v1 path to decode the old values
+    let v2 = VersionedCodec::<JobV2>::new(2)
+        .register(1, |job: JobV1| JobV2 {
+            name: job.name,
+            retries: 3,
+        })
+        .register(2, |job: JobV2| job);
+    v2.insert(
+        &db,
+        &2,
+        &JobV2 {
+            name: "flush".to_string(),
+            retries: 1,
+        },
+    )
+    .expect("Failed to insert");
+
+    assert_eq!(db.get(&1).unwrap().unwrap().version(), 1);
+    assert_eq!(db.get(&2).unwrap().unwrap().version(), 2);
+    assert_eq!(
+        v2.get(&db, &1).expect("Failed to get"),
+        Some(JobV2 {
+            name: "compact".to_string(),
+            retries: 3,
+        })
+    );
+    assert_eq!(
+        v2.get(&db, &2).expect("Failed to get"),
+        Some(JobV2 {
+            name: "flush".to_string(),
+            retries: 1,
+        })
+    );
+
+    // The v1 reader doesn't know about the newer schema
+    assert!(v1.get(&db, &2).is_err());
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{rocks, MultiStore, StoreError};
+
+fn temp_dir() -> std::path::PathBuf {
+    tempfile::tempdir()
+        .expect("Failed to open temporary directory")
+        .into_path()
+}
+
+const ACCOUNTS: &str = "accounts";
+const BALANCES: &str = "balances";
+
+fn open_multi_store() -> MultiStore<Vec<u8>, Vec<u8>> {
+    let rocks = rocks::open_cf(temp_dir(), None, &[ACCOUNTS, BALANCES]).unwrap();
+    let accounts = rocks::DBMap::reopen(&rocks, Some(ACCOUNTS)).unwrap();
+    let balances = rocks::DBMap::reopen(&rocks, Some(BALANCES)).unwrap();
+    MultiStore::new(vec![
+        (ACCOUNTS.to_string(), accounts),
+        (BALANCES.to_string(), balances),
+    ])
+}
+
+#[tokio::test]
+async fn write_and_read_two_namespaces() {
+    let store = open_multi_store();
+
+    // Write the same key to both namespaces.
+    let key = vec![0u8, 1u8, 2u8, 3u8];
+    store
+        .write(ACCOUNTS, key.clone(), vec![4u8, 5u8])
+        .await
+        .unwrap();
+    store
+        .write(BALANCES, key.clone(), vec![6u8, 7u8])
+        .await
+        .unwrap();
+
+    // Each namespace returns its own value.
+    let result = store.read(ACCOUNTS, key.clone()).await.unwrap();
+    assert_eq!(result, Some(vec![4u8, 5u8]));
+    let result = store.read(BALANCES, key.clone()).await.unwrap();
+    assert_eq!(result, Some(vec![6u8, 7u8]));
+
+    // Removing from one namespace leaves the other untouched.
+    store.remove(ACCOUNTS, key.clone()).await.unwrap();
+    assert!(store.read(ACCOUNTS, key.clone()).await.unwrap().is_none());
+    assert!(store.read(BALANCES, key).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn write_all_and_read_all_namespace() {
+    let store = open_multi_store();
+
+    let key_values = vec![
+        (vec![0u8, 1u8, 2u8, 1u8], vec![4u8, 5u8, 6u8, 7u8]),
+        (vec![0u8, 1u8, 2u8, 2u8], vec![4u8, 5u8, 6u8, 8u8]),
+    ];
+    store.write_all(ACCOUNTS, key_values.clone()).await.unwrap();
+
+    let keys: Vec<_> = key_values.iter().map(|(k, _)| k.clone()).collect();
+    let result = store.read_all(ACCOUNTS, keys.clone()).await.unwrap();
+    assert_eq!(
+        result,
+        key_values
+            .into_iter()
+            .map(|(_, v)| Some(v))
+            .collect::<Vec<_>>()
+    );
+
+    // The other namespace did not receive the batch.
+    let result = store.read_all(BALANCES, keys).await.unwrap();
+    assert!(result.iter().all(Option::is_none));
+}
+
+#[tokio::test]
+async fn unknown_namespace() {
+    let store = open_multi_store();
+
+    let result = store.read("unknown", vec![0u8]).await;
+    assert_eq!(
+        result,
+        Err(StoreError::UnregisteredColumn("unknown".to_string()))
+    );
+}
+
+/// A value whose serialization panics when it is armed.
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct Explosive(bool);
+
+impl serde::Serialize for Explosive {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        assert!(!self.0, "Explosive value serialized");
+        serializer.serialize_bool(self.0)
+    }
+}
+
+#[tokio::test]
+async fn panicked_task_is_reported() {
+    let db = rocks::DBMap::<u64, Explosive>::open(temp_dir(), None, None).unwrap();
+    let store = MultiStore::new(vec![(ACCOUNTS.to_string(), db)]);
+    store
+        .write_all(ACCOUNTS, vec![(1, Explosive(false))])
+        .await
+        .unwrap();
+    assert!(!store.has_panicked());
+
+    // The command that panics the background task fails with a clear error.
+    let result = store.write_all(ACCOUNTS, vec![(2, Explosive(true))]).await;
+    assert_eq!(result, Err(StoreError::BackgroundTaskPanicked));
+    assert!(store.has_panicked());
+
+    // So do the commands sent afterwards, fire-and-forget ones included.
+    assert_eq!(
+        store.read(ACCOUNTS, 1).await,
+        Err(StoreError::BackgroundTaskPanicked)
+    );
+    assert_eq!(
+        store.write(ACCOUNTS, 3, Explosive(false)).await,
+        Err(StoreError::BackgroundTaskPanicked)
+    );
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{rocks, Store, StoreError, StoreRequest, StoreResponse};
+use tower::{Service, ServiceExt};
+
+fn temp_dir() -> std::path::PathBuf {
+    tempfile::tempdir()
+        .expect("Failed to open temporary directory")
+        .into_path()
+}
+
+#[tokio::test]
+async fn service_serves_reads_and_writes() {
+    // GIVEN Create new store, as a service
+    let db = rocks::DBMap::<u32, String>::open(temp_dir(), None, None).unwrap();
+    let mut service = Store::new(db);
+
+    // WHEN writing through the service
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::WriteAll(vec![
+            (1, "one".to_string()),
+            (2, "two".to_string()),
+        ]))
+        .await;
+    assert_eq!(response, Ok(StoreResponse::Done));
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::Write(3, "three".to_string()))
+        .await;
+    assert_eq!(response, Ok(StoreResponse::Done));
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::Remove(2))
+        .await;
+    assert_eq!(response, Ok(StoreResponse::Done));
+
+    // THEN the reads through the service observe the writes
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::Read(1))
+        .await;
+    assert_eq!(response, Ok(StoreResponse::Value(Some("one".to_string()))));
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::ReadAll(vec![1, 2, 3]))
+        .await;
+    assert_eq!(
+        response,
+        Ok(StoreResponse::Values(vec![
+            Some("one".to_string()),
+            None,
+            Some("three".to_string()),
+        ]))
+    );
+
+    // AND the same store serves them through its methods
+    assert_eq!(service.read(3).await, Ok(Some("three".to_string())));
+}
+
+#[tokio::test]
+async fn service_reports_failed_writes() {
+    // GIVEN Create new store, as a service, and close it
+    let db = rocks::DBMap::<u32, String>::open(temp_dir(), None, None).unwrap();
+    let mut service = Store::new(db);
+    service.clone().flush_and_close().await.unwrap();
+
+    // WHEN writing and removing single keys through the service
+    // THEN the failures are reported, as the requests are acknowledged
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::Write(1, "one".to_string()))
+        .await;
+    assert_eq!(response, Err(StoreError::StoreClosed));
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(StoreRequest::Remove(1))
+        .await;
+    assert_eq!(response, Err(StoreError::StoreClosed));
+}
@@ -138,3 +138,2016 @@ async fn write_and_read_all_successfully() {
         assert_eq!(value.unwrap(), key_values[i].1);
     }
 }
+
+#[tokio::test]
+async fn read_with_deadline_behind_writes() {
+    // GIVEN a store running on its own thread
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new_on_dedicated_thread(db);
+
+    // AND its background task stalled, until released
+    let (release, stalled) = std::sync::mpsc::channel::<()>();
+    let stall = move |_: &mut dyn Iterator<Item = (&Vec<u8>, std::time::Duration)>| {
+        let _ = stalled.recv();
+    };
+    if store
+        .channel
+        .send(StoreCommand::InspectObligations(Box::new(stall)))
+        .await
+        .is_err()
+    {
+        panic!("Failed to stall the store");
+    }
+
+    // AND a large batch queued ahead of the read
+    let store_copy = store.clone();
+    let writer = tokio::spawn(async move {
+        let key_values = (0..1_000u32).map(|j| (j.to_be_bytes().to_vec(), vec![0u8; 128]));
+        store_copy.write_all(key_values).await
+    });
+    tokio::task::yield_now().await;
+
+    // WHEN reading with a tight deadline, on a clock that only advances once the test
+    // has nothing left to do
+    tokio::time::pause();
+    let deadline = Instant::now() + std::time::Duration::from_millis(1);
+    let result = store
+        .read_with_deadline(vec![0u8, 0u8, 0u8], deadline)
+        .await;
+
+    // THEN the read gives up rather than waiting for the writes
+    assert_eq!(result, Err(StoreError::Deadline));
+
+    // AND the writes still complete once the store is released
+    release.send(()).unwrap();
+    assert!(writer.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn read_with_deadline_successfully() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND Write a value to the store.
+    let key = vec![0u8, 1u8, 2u8, 3u8];
+    let value = vec![4u8, 5u8, 6u8, 7u8];
+    store.write(key.clone(), value.clone()).await;
+
+    // WHEN reading with a generous deadline
+    let deadline = Instant::now() + std::time::Duration::from_secs(10);
+    let result = store.read_with_deadline(key.clone(), deadline).await;
+
+    // THEN the value is returned
+    assert_eq!(result, Ok(Some(value.clone())));
+    let result = store.read_all_with_deadline(vec![key], deadline).await;
+    assert_eq!(result, Ok(vec![Some(value)]));
+}
+
+#[tokio::test]
+async fn flush_and_close_persists_data() {
+    // GIVEN Create new store.
+    let path = temp_dir();
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(&path, None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND Write values to the store.
+    let key_values = vec![
+        (vec![0u8, 1u8, 2u8, 1u8], vec![4u8, 5u8, 6u8, 7u8]),
+        (vec![0u8, 1u8, 2u8, 2u8], vec![4u8, 5u8, 6u8, 7u8]),
+        (vec![0u8, 1u8, 2u8, 3u8], vec![4u8, 5u8, 6u8, 7u8]),
+    ];
+    store.write_all(key_values.clone()).await.unwrap();
+    store.write(vec![0u8, 1u8, 2u8, 4u8], vec![8u8, 9u8]).await;
+
+    // AND a pending notify read
+    let store_copy = store.clone();
+    let handle = tokio::spawn(async move { store_copy.notify_read(vec![9u8]).await });
+    tokio::task::yield_now().await;
+
+    // WHEN
+    let result = store.flush_and_close().await;
+
+    // THEN
+    assert!(result.is_ok());
+    assert_eq!(handle.await.unwrap(), Err(StoreError::StoreClosed));
+
+    // AND the data is there once the DB is reopened
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(&path, None, None).unwrap();
+    for (key, value) in key_values {
+        assert_eq!(db.get(&key).unwrap(), Some(value));
+    }
+    assert_eq!(
+        db.get(&vec![0u8, 1u8, 2u8, 4u8]).unwrap(),
+        Some(vec![8u8, 9u8])
+    );
+}
+
+#[tokio::test]
+async fn sync_on_drop_persists_data() {
+    // GIVEN a store syncing on drop, whose task is run by the test
+    let path = temp_dir();
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(&path, None, None).unwrap();
+    let config = StoreConfig {
+        sync_on_drop: true,
+        ..Default::default()
+    };
+    let (store, task) = Store::with_task(db, config);
+    let task = tokio::spawn(task);
+
+    // AND values written right before the drop
+    store.write(vec![1u8], vec![2u8]).await;
+    store
+        .write_all(vec![(vec![3u8], vec![4u8]), (vec![5u8], vec![6u8])])
+        .await
+        .unwrap();
+    store.write(vec![7u8], vec![8u8]).await;
+
+    // WHEN the last clone of the store is dropped
+    drop(store);
+    task.await.unwrap();
+
+    // THEN the data is there once the DB is reopened
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(&path, None, None).unwrap();
+    for (key, value) in [(1u8, 2u8), (3, 4), (5, 6), (7, 8)] {
+        assert_eq!(db.get(&vec![key]).unwrap(), Some(vec![value]));
+    }
+}
+
+#[tokio::test]
+async fn write_all_if_absent_rejects_existing_key() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND one pre-existing key
+    let existing = vec![0u8, 1u8, 2u8, 2u8];
+    store.write(existing.clone(), vec![9u8]).await;
+
+    // WHEN writing a batch that contains it
+    let key_values = vec![
+        (vec![0u8, 1u8, 2u8, 1u8], vec![4u8, 5u8, 6u8, 7u8]),
+        (existing.clone(), vec![4u8, 5u8, 6u8, 7u8]),
+        (vec![0u8, 1u8, 2u8, 3u8], vec![4u8, 5u8, 6u8, 7u8]),
+    ];
+    let result = store.write_all_if_absent(key_values.clone()).await;
+
+    // THEN the whole batch is rejected
+    assert_eq!(result, Ok(false));
+
+    // AND the DB is unchanged
+    let keys: Vec<Vec<u8>> = key_values.into_iter().map(|(key, _)| key).collect();
+    let result = store.read_all(keys).await.unwrap();
+    assert_eq!(result, vec![None, Some(vec![9u8]), None]);
+}
+
+#[tokio::test]
+async fn write_all_if_absent_successfully() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // WHEN writing a batch of absent keys
+    let key_values = vec![
+        (vec![0u8, 1u8, 2u8, 1u8], vec![4u8, 5u8, 6u8, 7u8]),
+        (vec![0u8, 1u8, 2u8, 2u8], vec![4u8, 5u8, 6u8, 8u8]),
+    ];
+    let result = store.write_all_if_absent(key_values.clone()).await;
+
+    // THEN the batch is written
+    assert_eq!(result, Ok(true));
+    let keys: Vec<Vec<u8>> = key_values.iter().map(|(key, _)| key.clone()).collect();
+    let result = store.read_all(keys).await.unwrap();
+    assert_eq!(
+        result,
+        key_values
+            .into_iter()
+            .map(|(_, value)| Some(value))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn notify_read_records_wait_time() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<Vec<u8>, Vec<u8>>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    let key = vec![0u8, 1u8, 2u8, 3u8];
+    let value = vec![4u8, 5u8, 6u8, 7u8];
+
+    // AND a notify read registered before the value is written
+    let store_copy = store.clone();
+    let key_copy = key.clone();
+    let handle = tokio::spawn(async move { store_copy.notify_read(key_copy).await });
+    tokio::task::yield_now().await;
+
+    // WHEN the write is delayed
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    store.write(key.clone(), value.clone()).await;
+    let result = handle.await.unwrap();
+    assert_eq!(result.unwrap(), Some(value));
+
+    // AND a notify read is issued for the now present value
+    store.notify_read(key).await.unwrap();
+
+    // THEN the wait has been recorded
+    let metrics = store.metrics();
+    assert_eq!(metrics.notify_read_waited(), 1);
+    assert_eq!(metrics.notify_read_immediate(), 1);
+    assert!(metrics.notify_read_wait_total() >= std::time::Duration::from_millis(20));
+    let recorded: u64 = metrics
+        .notify_read_wait_histogram()
+        .iter()
+        .map(|(_, count)| count)
+        .sum();
+    assert_eq!(recorded, 1);
+}
+
+#[tokio::test]
+async fn write_all_notifies_concurrent_readers() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // WHEN notify reads and batch writes on the same keys are interleaved
+    let mut readers = Vec::new();
+    let mut writers = Vec::new();
+    for batch in 0..10 {
+        let keys: Vec<usize> = (batch * 10..(batch + 1) * 10).collect();
+        for key in keys.iter().copied() {
+            let store_copy = store.clone();
+            readers.push(tokio::spawn(async move {
+                (key, store_copy.notify_read(key).await)
+            }));
+        }
+        let store_copy = store.clone();
+        writers.push(tokio::spawn(async move {
+            store_copy
+                .write_all(keys.into_iter().map(|key| (key, key.to_string())))
+                .await
+        }));
+        // AND some readers register after the batch has been sent
+        for key in batch * 10..batch * 10 + 5 {
+            let store_copy = store.clone();
+            readers.push(tokio::spawn(async move {
+                (key, store_copy.notify_read(key).await)
+            }));
+        }
+    }
+
+    // THEN every write succeeds
+    for writer in writers {
+        assert!(writer.await.unwrap().is_ok());
+    }
+
+    // AND every reader is resolved with the value written for its key
+    let all_readers = async {
+        for reader in readers {
+            let (key, result) = reader.await.unwrap();
+            assert_eq!(result.unwrap(), Some(key.to_string()));
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(10), all_readers)
+        .await
+        .expect("A notify read was never resolved");
+}
+
+#[tokio::test]
+async fn scan_prefix_page_covers_prefix() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<(u32, u32), String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND a prefix of 200 entries, surrounded by entries of other prefixes
+    for group in [6u32, 7, 8] {
+        let key_values = (0..200).map(|i| ((group, 2 * i), format!("{group}-{}", 2 * i)));
+        store.write_all(key_values).await.unwrap();
+    }
+
+    // WHEN scanning the prefix in pages of 30
+    let mut scanned = Vec::new();
+    let mut cursor = None;
+    let mut pages = 0;
+    loop {
+        let (page, next) = store.scan_prefix_page(7u32, cursor, 30).await.unwrap();
+        assert!(page.len() <= 30);
+        scanned.extend(page);
+        pages += 1;
+        if pages == 2 {
+            // AND a key is inserted in the prefix, before the cursor
+            store.write((7, 1), "7-1".to_string()).await;
+        }
+        match next {
+            Some(key) => cursor = Some(key),
+            None => break,
+        }
+    }
+
+    // THEN the pages cover the whole prefix, without overlapping
+    assert_eq!(pages, 7);
+    let keys: Vec<_> = scanned.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, (0..200).map(|i| (7, 2 * i)).collect::<Vec<_>>());
+    assert!(scanned
+        .iter()
+        .all(|((group, i), value)| value == &format!("{group}-{i}")));
+}
+
+#[tokio::test]
+async fn scan_prefix_page_rejects_empty_pages() {
+    // GIVEN Create new store, with entries in a prefix
+    let db = rocks::DBMap::<(u32, u32), String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store.write((7, 1), "7-1".to_string()).await;
+
+    // WHEN scanning pages of no entries
+    let result = store.scan_prefix_page(7u32, None, 0).await;
+
+    // THEN the scan is rejected, rather than returning a cursor that never advances
+    assert_eq!(result, Err(StoreError::InvalidScanBounds));
+}
+
+#[tokio::test]
+async fn read_range_ordered_sorts_by_key() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<u64, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND keys written out of order
+    for key in [42u64, 7, 300, 1, 19, 256, 1000] {
+        store.write(key, key.to_string()).await;
+    }
+
+    // WHEN reading a range spanning them
+    let entries = store.read_range_ordered(5, 500).await.unwrap();
+
+    // THEN the entries within the range come back in ascending key order
+    let expected: Vec<_> = [7u64, 19, 42, 256, 300]
+        .into_iter()
+        .map(|key| (key, key.to_string()))
+        .collect();
+    assert_eq!(entries, expected);
+}
+
+#[tokio::test]
+async fn obligation_cleanup_removes_dropped_notify_reads() {
+    // GIVEN Create new store sweeping its obligations.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        obligation_cleanup_interval: Some(std::time::Duration::from_millis(10)),
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+
+    // AND many notify reads whose caller gives up on them
+    for key in 0..100 {
+        let notify_read = store.notify_read(key);
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(1), notify_read).await;
+    }
+    // AND one that is still awaited
+    let store_copy = store.clone();
+    let handle = tokio::spawn(async move { store_copy.notify_read(100).await });
+    tokio::task::yield_now().await;
+
+    // WHEN a sweep interval elapses
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    // THEN only the awaited notify read is left
+    assert_eq!(store.metrics().notify_read_pending(), 1);
+
+    // AND it is still resolved by a write
+    store.write(100, "100".to_string()).await;
+    assert_eq!(handle.await.unwrap().unwrap(), Some("100".to_string()));
+    assert_eq!(store.metrics().notify_read_pending(), 0);
+}
+
+#[tokio::test]
+async fn empty_batches_skip_the_store() {
+    // GIVEN a store whose background task is gone
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store.clone().flush_and_close().await.unwrap();
+
+    // WHEN issuing batches of no keys
+    // THEN they return the trivial result, rather than failing to reach the store
+    assert!(store.write_all(Vec::new()).await.is_ok());
+    assert!(store
+        .write_all_traced(Vec::new(), "request-1")
+        .await
+        .is_ok());
+    assert!(store.write_all_if_absent(Vec::new()).await.unwrap());
+    assert!(store.remove_all(Vec::new()).await.is_ok());
+    assert!(store.read_all(Vec::new()).await.unwrap().is_empty());
+    let deadline = Instant::now() + std::time::Duration::from_secs(1);
+    assert!(store
+        .read_all_with_deadline(Vec::new(), deadline)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn store_on_provided_runtime() {
+    // GIVEN a runtime driven by a thread of its own
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let handle = runtime.handle().clone();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let driver = std::thread::spawn(move || {
+        let _ = runtime.block_on(stopped);
+    });
+
+    // WHEN a store is created on that runtime
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new_on(db, handle);
+
+    // THEN it serves the commands sent from the test runtime
+    store.write(1, "1".to_string()).await;
+    assert_eq!(store.read(1).await.unwrap(), Some("1".to_string()));
+
+    stop.send(()).unwrap();
+    driver.join().unwrap();
+}
+
+#[tokio::test]
+async fn store_on_dedicated_thread() {
+    // GIVEN a store running on its own thread
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new_on_dedicated_thread(db);
+
+    // WHEN writing through it
+    store.write_all(vec![(1, "1".to_string())]).await.unwrap();
+
+    // THEN the value can be read back, notifications included
+    assert_eq!(store.notify_read(1).await.unwrap(), Some("1".to_string()));
+    store.flush_and_close().await.unwrap();
+}
+
+#[tokio::test]
+async fn store_on_dedicated_thread_with_config() {
+    // GIVEN a store running on its own thread, without notify_read
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        enable_notify_read: false,
+        ..StoreConfig::default()
+    };
+    let store = Store::new_on_dedicated_thread_with_config(db, config);
+
+    // WHEN writing through it
+    store.write_all(vec![(1, "1".to_string())]).await.unwrap();
+
+    // THEN the configuration applies
+    assert_eq!(store.read(1).await.unwrap(), Some("1".to_string()));
+    assert!(matches!(
+        store.notify_read(1).await,
+        Err(StoreError::NotifyReadDisabled)
+    ));
+    store.flush_and_close().await.unwrap();
+}
+
+#[test]
+fn dedup_multi_get_looks_up_distinct_keys() {
+    // GIVEN keys with many duplicates, some of them missing
+    let keys: Vec<usize> = (0..100).map(|i| i % 7).collect();
+    let lookup = |key: usize| if key == 3 { None } else { Some(key * 10) };
+
+    // WHEN reading them through a counting lookup
+    let mut looked_up = 0;
+    let result = dedup_multi_get(&keys, |distinct| {
+        looked_up += distinct.len();
+        Ok(distinct.into_iter().map(|key| lookup(*key)).collect())
+    });
+
+    // THEN the result matches one lookup per key, in the same order
+    let naive: Vec<_> = keys.iter().map(|key| lookup(*key)).collect();
+    assert_eq!(result.unwrap(), naive);
+    // AND each distinct key was looked up once
+    assert_eq!(looked_up, 7);
+}
+
+#[tokio::test]
+async fn read_all_with_duplicate_keys() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store
+        .write_all(vec![(1, "1".to_string()), (2, "2".to_string())])
+        .await
+        .unwrap();
+
+    // WHEN reading duplicate keys
+    let result = store.read_all(vec![2, 1, 3, 2, 2, 1]).await.unwrap();
+
+    // THEN every position gets the value of its key
+    let one = Some("1".to_string());
+    let two = Some("2".to_string());
+    assert_eq!(
+        result,
+        vec![two.clone(), one.clone(), None, two.clone(), two, one]
+    );
+}
+
+#[tokio::test]
+async fn health_check_fails_on_closed_store() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let timeout = std::time::Duration::from_secs(1);
+
+    // THEN it is healthy while running
+    assert!(store.health_check(timeout).await.is_ok());
+
+    // WHEN it is closed
+    store.clone().flush_and_close().await.unwrap();
+
+    // THEN it reports as such
+    assert_eq!(
+        store.health_check(timeout).await,
+        Err(StoreError::StoreClosed)
+    );
+}
+
+#[tokio::test]
+async fn health_check_times_out_on_stalled_store() {
+    // GIVEN a store running on its own thread
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new_on_dedicated_thread(db);
+
+    // AND its background task stalled, until released
+    let (release, stalled) = std::sync::mpsc::channel::<()>();
+    let stall = move |_: &mut dyn Iterator<Item = (&usize, std::time::Duration)>| {
+        let _ = stalled.recv();
+    };
+    if store
+        .channel
+        .send(StoreCommand::InspectObligations(Box::new(stall)))
+        .await
+        .is_err()
+    {
+        panic!("Failed to stall the store");
+    }
+
+    // WHEN checking its health, on a clock that only advances once the test has nothing
+    // left to do
+    tokio::time::pause();
+    let result = store
+        .health_check(std::time::Duration::from_millis(1))
+        .await;
+
+    // THEN the check times out
+    assert_eq!(result, Err(StoreError::Timeout));
+
+    // AND the store is healthy again once released
+    release.send(()).unwrap();
+    tokio::time::resume();
+    assert!(store
+        .health_check(std::time::Duration::from_secs(1))
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn commands_fail_on_closed_store() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store.write_all(vec![(1, "1".to_string())]).await.unwrap();
+
+    // WHEN it is closed through another clone
+    store.clone().flush_and_close().await.unwrap();
+
+    // THEN the commands sent afterwards report it, rather than panicking
+    assert_eq!(store.read(1).await, Err(StoreError::StoreClosed));
+    assert_eq!(
+        store.write_all(vec![(2, "2".to_string())]).await,
+        Err(StoreError::StoreClosed)
+    );
+    assert_eq!(store.notify_read(2).await, Err(StoreError::StoreClosed));
+    assert_eq!(
+        store.write_nonblocking(2, "2".to_string()),
+        Err(StoreError::StoreClosed)
+    );
+    assert_eq!(
+        store.clone().flush_and_close().await,
+        Err(StoreError::StoreClosed)
+    );
+    // AND the unacknowledged commands are dropped
+    store.write(2, "2".to_string()).await;
+    store.remove(1).await;
+}
+
+/// The name and the fields of each span created, in order.
+type RecordedSpans = std::sync::Arc<std::sync::Mutex<Vec<(String, Vec<(String, String)>)>>>;
+
+/// Records the name and fields of every span created while it is the default subscriber.
+#[derive(Default)]
+struct SpanRecorder {
+    spans: RecordedSpans,
+    next_id: std::sync::atomic::AtomicU64,
+}
+
+#[derive(Default)]
+struct FieldRecorder(Vec<(String, String)>);
+
+impl tracing::field::Visit for FieldRecorder {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .push((field.name().to_string(), format!("{value:?}")));
+    }
+}
+
+impl tracing::Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut fields = FieldRecorder::default();
+        span.record(&mut fields);
+        self.spans
+            .lock()
+            .unwrap()
+            .push((span.metadata().name().to_string(), fields.0));
+        let id = self
+            .next_id
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        tracing::span::Id::from_u64(id + 1)
+    }
+
+    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, _event: &tracing::Event<'_>) {}
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
+}
+
+#[tokio::test]
+async fn traced_commands_carry_request_id() {
+    // GIVEN a subscriber recording the spans of this (single threaded) runtime
+    let recorder = SpanRecorder::default();
+    let recorded_spans = recorder.spans.clone();
+    let _guard = tracing::subscriber::set_default(recorder);
+
+    // AND Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // WHEN commands are sent with a request ID
+    store.write_traced(1, "1".to_string(), "request-42").await;
+    let result = store.read_traced(1, "request-43").await;
+    assert_eq!(result.unwrap(), Some("1".to_string()));
+
+    // THEN the store processed them in spans carrying their ID
+    let spans = recorded_spans.lock().unwrap();
+    for request_id in ["request-42", "request-43"] {
+        assert!(spans.iter().any(|(name, fields)| {
+            name == "store_command"
+                && fields.contains(&("request_id".to_string(), request_id.to_string()))
+        }));
+    }
+}
+
+#[tokio::test]
+async fn get_defined_tells_stored_none_from_absent() {
+    // GIVEN Create new store of optional values.
+    let db = rocks::DBMap::<usize, Option<String>>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // WHEN storing a None and a Some
+    store.write(1, None).await;
+    store.write(2, Some("2".to_string())).await;
+
+    // THEN the three cases are told apart
+    assert_eq!(store.get_defined(1).await.unwrap(), Presence::StoredNone);
+    assert_eq!(
+        store.get_defined(2).await.unwrap(),
+        Presence::Present("2".to_string())
+    );
+    assert_eq!(store.get_defined(3).await.unwrap(), Presence::Absent);
+}
+
+#[tokio::test]
+async fn contains_all_successfully() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND the even keys are stored
+    let key_values = (0..20).step_by(2).map(|key| (key, key.to_string()));
+    store.write_all(key_values).await.unwrap();
+
+    // WHEN checking a mix of present and absent keys
+    let keys = vec![0, 1, 2, 3, 18, 19, 100, 4];
+    let result = store.contains_all(keys.clone()).await.unwrap();
+
+    // THEN
+    let expected: Vec<_> = keys.iter().map(|key| key % 2 == 0 && *key < 20).collect();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn blocking_store_from_plain_thread() {
+    // GIVEN a store served by a runtime driven by a thread of its own
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let handle = runtime.handle().clone();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let driver = std::thread::spawn(move || {
+        let _ = runtime.block_on(stopped);
+    });
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = BlockingStore::new(Store::new_on(db, handle.clone()), handle);
+
+    // WHEN it is used from a thread that runs no async code
+    let worker = std::thread::spawn(move || {
+        store.write(1, "1".to_string());
+        store
+            .write_all(vec![(2, "2".to_string()), (3, "3".to_string())])
+            .unwrap();
+        store.remove(3);
+        (store.read(1).unwrap(), store.read_all(vec![2, 3]).unwrap())
+    });
+
+    // THEN the calls complete, in order
+    let (one, others) = worker.join().unwrap();
+    assert_eq!(one, Some("1".to_string()));
+    assert_eq!(others, vec![Some("2".to_string()), None]);
+
+    stop.send(()).unwrap();
+    driver.join().unwrap();
+}
+
+#[tokio::test]
+async fn prioritized_reads_jump_ahead_of_writes() {
+    // GIVEN Create new store prioritizing reads, running on its own thread.
+    let db = rocks::DBMap::<usize, usize>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        prioritize_reads: true,
+        ..Default::default()
+    };
+    let store = Store::new_on_dedicated_thread_with_config(db, config);
+    store.write_all(vec![(0, 0)]).await.unwrap();
+
+    // AND its background task stalled, until released
+    let (release, stalled) = std::sync::mpsc::channel::<()>();
+    let stall = move |_: &mut dyn Iterator<Item = (&usize, std::time::Duration)>| {
+        let _ = stalled.recv();
+    };
+    if store
+        .channel
+        .send(StoreCommand::InspectObligations(Box::new(stall)))
+        .await
+        .is_err()
+    {
+        panic!("Failed to stall the store");
+    }
+
+    // AND a backlog of large writes queued behind it, all overwriting the same key last
+    let writers: Vec<_> = (1..=20)
+        .map(|i| {
+            let store_copy = store.clone();
+            tokio::spawn(async move {
+                let key_values = (1..1000).map(|key| (key, i)).chain(std::iter::once((0, i)));
+                store_copy.write_all(key_values).await
+            })
+        })
+        .collect();
+    while store.channel.capacity() > COMMAND_CHANNEL_CAPACITY - 20 {
+        tokio::task::yield_now().await;
+    }
+
+    // WHEN reading behind the backlog, once the store is released
+    let read = store.read(0);
+    tokio::pin!(read);
+    assert!(futures::poll!(&mut read).is_pending());
+    release.send(()).unwrap();
+    let result = read.await.unwrap();
+
+    // THEN the read is served before the writes
+    assert_eq!(result, Some(0));
+
+    // AND the writes are all applied, in order
+    for writer in writers {
+        writer.await.unwrap().unwrap();
+    }
+    assert_eq!(store.read(0).await.unwrap(), Some(20));
+}
+
+#[tokio::test]
+async fn write_if_changed_skips_unchanged_values() {
+    // GIVEN Create new store
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND a reader waiting on a key
+    let store_copy = store.clone();
+    let reader = tokio::spawn(async move { store_copy.notify_read(1).await });
+    tokio::task::yield_now().await;
+
+    // WHEN writing the key a first time
+    let changed = store.write_if_changed(1, "one".to_string()).await.unwrap();
+
+    // THEN the value is written, and the reader released
+    assert!(changed);
+    assert_eq!(reader.await.unwrap().unwrap(), Some("one".to_string()));
+    assert_eq!(store.metrics().notify_read_waited(), 1);
+
+    // WHEN writing the same value again
+    let changed = store.write_if_changed(1, "one".to_string()).await.unwrap();
+
+    // THEN nothing changes
+    assert!(!changed);
+
+    // WHEN writing a different value
+    let changed = store.write_if_changed(1, "uno".to_string()).await.unwrap();
+
+    // THEN the value is overwritten
+    assert!(changed);
+    assert_eq!(store.read(1).await.unwrap(), Some("uno".to_string()));
+}
+
+#[tokio::test]
+async fn writes_beyond_max_in_flight_are_rejected() {
+    // GIVEN Create new store allowing two writes in flight, running on its own thread
+    let db = rocks::DBMap::<usize, usize>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        max_in_flight: Some(2),
+        ..Default::default()
+    };
+    let store = Store::new_on_dedicated_thread_with_config(db, config);
+
+    // AND its background task stalled, until released
+    let (release, stalled) = std::sync::mpsc::channel::<()>();
+    let stall = move |_: &mut dyn Iterator<Item = (&usize, std::time::Duration)>| {
+        let _ = stalled.recv();
+    };
+    if store
+        .channel
+        .send(StoreCommand::InspectObligations(Box::new(stall)))
+        .await
+        .is_err()
+    {
+        panic!("Failed to stall the store");
+    }
+
+    // AND two writes queued behind it, awaiting their reply
+    let writers: Vec<_> = (0..2)
+        .map(|i| {
+            let store_copy = store.clone();
+            tokio::spawn(async move { store_copy.write_all(vec![(i, i)]).await })
+        })
+        .collect();
+    while store.channel.capacity() > COMMAND_CHANNEL_CAPACITY - 2 {
+        tokio::task::yield_now().await;
+    }
+
+    // WHEN writing once more
+    let result = store.write_all(vec![(2, 2)]).await;
+
+    // THEN the write is rejected, and nothing written
+    assert_eq!(result, Err(StoreError::Overloaded));
+    release.send(()).unwrap();
+    for writer in writers {
+        writer.await.unwrap().unwrap();
+    }
+    assert_eq!(store.read(2).await.unwrap(), None);
+
+    // AND writes are accepted again once the writes in flight are acknowledged
+    store.write_all(vec![(2, 2)]).await.unwrap();
+    assert_eq!(store.read(2).await.unwrap(), Some(2));
+}
+
+#[tokio::test]
+async fn reader_sees_writes_of_the_store() {
+    // GIVEN Create new store, and a reader of it
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let reader = store.reader();
+
+    // AND a component waiting on a key through a clone of the reader
+    let reader_copy = reader.clone();
+    let waiter = tokio::spawn(async move { reader_copy.notify_read(2).await });
+
+    // WHEN writing through the store
+    store
+        .write_all(vec![(1, "one".to_string()), (2, "two".to_string())])
+        .await
+        .unwrap();
+
+    // THEN the reader reads the values written
+    assert_eq!(reader.read(1).await.unwrap(), Some("one".to_string()));
+    assert_eq!(
+        reader.read_all(vec![1, 3]).await.unwrap(),
+        vec![Some("one".to_string()), None]
+    );
+    assert_eq!(
+        reader.contains_all(vec![2, 3]).await.unwrap(),
+        vec![true, false]
+    );
+    assert_eq!(waiter.await.unwrap().unwrap(), Some("two".to_string()));
+}
+
+/// A key that can't be cloned.
+#[derive(Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Unclonable(usize);
+
+#[tokio::test]
+async fn handles_clone_without_clonable_keys() {
+    // GIVEN Create new stores, whose keys can't be cloned
+    let db = rocks::DBMap::<Unclonable, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let db = rocks::DBMap::<Unclonable, String>::open(temp_dir(), None, None).unwrap();
+    let multi_store = MultiStore::new(vec![("table".to_string(), db)]);
+
+    // WHEN cloning the handles over them
+    let reader = store.reader().clone();
+    let blocking = BlockingStore::new(store.clone(), tokio::runtime::Handle::current()).clone();
+    let multi_store_copy = multi_store.clone();
+
+    // THEN the clones serve the same stores
+    store.write(Unclonable(1), "one".to_string()).await;
+    assert_eq!(
+        reader.read(Unclonable(1)).await.unwrap(),
+        Some("one".to_string())
+    );
+    assert_eq!(
+        blocking.store().read(Unclonable(1)).await.unwrap(),
+        Some("one".to_string())
+    );
+    multi_store
+        .write("table", Unclonable(2), "two".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        multi_store_copy.read("table", Unclonable(2)).await.unwrap(),
+        Some("two".to_string())
+    );
+}
+
+/// A value whose serialization panics when it is armed.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+struct Explosive(bool);
+
+impl serde::Serialize for Explosive {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        assert!(!self.0, "Explosive value serialized");
+        serializer.serialize_bool(self.0)
+    }
+}
+
+#[tokio::test]
+async fn panicked_task_is_reported() {
+    // GIVEN Create new store
+    let db = rocks::DBMap::<usize, Explosive>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store.write_all(vec![(1, Explosive(false))]).await.unwrap();
+    assert!(!store.has_panicked());
+
+    // WHEN the background task panics while serving a command
+    let result = store.write_all(vec![(2, Explosive(true))]).await;
+
+    // THEN the command fails with a clear error
+    assert_eq!(result, Err(StoreError::BackgroundTaskPanicked));
+    assert!(store.has_panicked());
+
+    // AND so do the commands sent afterwards
+    assert_eq!(store.read(1).await, Err(StoreError::BackgroundTaskPanicked));
+    assert_eq!(
+        store.health_check(std::time::Duration::from_secs(1)).await,
+        Err(StoreError::BackgroundTaskPanicked)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn panicked_task_is_reported_multi_thread() {
+    // GIVEN Create new store, whose background task unwinds on another thread
+    let db = rocks::DBMap::<usize, Explosive>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store.write_all(vec![(1, Explosive(false))]).await.unwrap();
+    assert!(!store.has_panicked());
+
+    // WHEN the background task panics while serving a command
+    let result = store.write_all(vec![(2, Explosive(true))]).await;
+
+    // THEN the command fails with a clear error
+    assert_eq!(result, Err(StoreError::BackgroundTaskPanicked));
+    assert!(store.has_panicked());
+
+    // AND so do the commands sent afterwards
+    assert_eq!(store.read(1).await, Err(StoreError::BackgroundTaskPanicked));
+    assert_eq!(
+        store.health_check(std::time::Duration::from_secs(1)).await,
+        Err(StoreError::BackgroundTaskPanicked)
+    );
+    assert_eq!(
+        store.write_nonblocking(3, Explosive(false)),
+        Err(StoreError::BackgroundTaskPanicked)
+    );
+}
+
+#[tokio::test]
+async fn notify_read_any_returns_first_key_written() {
+    // GIVEN Create new store
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND a reader waiting on any of three keys
+    let store_copy = store.clone();
+    let reader = tokio::spawn(async move { store_copy.notify_read_any(vec![1, 2, 3]).await });
+    tokio::task::yield_now().await;
+    // a read queued behind the registration is served once it is registered
+    assert_eq!(store.read(1).await.unwrap(), None);
+    assert_eq!(store.metrics().notify_read_pending(), 1);
+
+    // WHEN writing one of them
+    store.write(2, "two".to_string()).await;
+
+    // THEN the reader gets that key and its value
+    assert_eq!(reader.await.unwrap().unwrap(), (2, "two".to_string()));
+    assert_eq!(store.metrics().notify_read_pending(), 0);
+
+    // AND a key already written is returned right away
+    store.write(3, "three".to_string()).await;
+    let result = store.notify_read_any(vec![1, 3, 2]).await.unwrap();
+    assert_eq!(result, (3, "three".to_string()));
+}
+
+#[test]
+fn any_obligations_are_removed_from_all_their_keys() {
+    // GIVEN two reads waiting on overlapping keys
+    let metrics = StoreMetrics::default();
+    let mut any_obligations = AnyObligations::<usize, String>::default();
+    let serialized = |keys: &[usize]| {
+        keys.iter()
+            .map(|key| (rocks::be_fix_int_ser(key).unwrap(), *key))
+            .collect()
+    };
+    let (sender, mut first) = oneshot::channel();
+    any_obligations.register(serialized(&[1, 2, 3]), sender);
+    let (sender, mut second) = oneshot::channel();
+    any_obligations.register(serialized(&[3, 4]), sender);
+
+    // WHEN one of the keys of the first read only is written
+    any_obligations.notify(&2, &"two".to_string(), &metrics);
+
+    // THEN the first read resolves, and is no longer registered on its other keys
+    assert_eq!(first.try_recv().unwrap().unwrap(), (2, "two".to_string()));
+    assert_eq!(any_obligations.reads.len(), 1);
+    let mut keys: Vec<_> = any_obligations.by_key.keys().cloned().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            rocks::be_fix_int_ser(&3usize).unwrap(),
+            rocks::be_fix_int_ser(&4usize).unwrap()
+        ]
+    );
+    assert!(second.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn remove_all_existing_returns_present_keys() {
+    // GIVEN Create new store with some keys
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store
+        .write_all(vec![(1, "one".to_string()), (3, "three".to_string())])
+        .await
+        .unwrap();
+
+    // WHEN removing a mix of present and absent keys
+    let removed = store.remove_all_existing(vec![4, 3, 2, 1]).await.unwrap();
+
+    // THEN only the present keys are returned
+    assert_eq!(removed, vec![3, 1]);
+    // AND all the keys are gone
+    assert_eq!(
+        store.contains_all(vec![1, 2, 3, 4]).await.unwrap(),
+        vec![false; 4]
+    );
+}
+
+#[tokio::test]
+async fn compare_and_delete_releases_owned_lock() {
+    // GIVEN Create new store holding a lock
+    let db = rocks::DBMap::<String, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let lock = "lock".to_string();
+    store.write(lock.clone(), "owner-1".to_string()).await;
+
+    // WHEN releasing it as its owner
+    let released = store
+        .compare_and_delete(lock.clone(), "owner-1".to_string())
+        .await
+        .unwrap();
+
+    // THEN it is deleted
+    assert!(released);
+    assert_eq!(store.read(lock).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn compare_and_delete_keeps_stolen_lock() {
+    // GIVEN Create new store holding a lock taken over by another owner
+    let db = rocks::DBMap::<String, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let lock = "lock".to_string();
+    store.write(lock.clone(), "owner-2".to_string()).await;
+
+    // WHEN releasing it as its former owner
+    let released = store
+        .compare_and_delete(lock.clone(), "owner-1".to_string())
+        .await
+        .unwrap();
+
+    // THEN it is kept
+    assert!(!released);
+    assert_eq!(store.read(lock).await.unwrap(), Some("owner-2".to_string()));
+}
+
+#[tokio::test]
+async fn compare_and_delete_absent_key() {
+    // GIVEN Create new store
+    let db = rocks::DBMap::<String, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // WHEN releasing a lock nobody holds
+    let released = store
+        .compare_and_delete("lock".to_string(), "owner-1".to_string())
+        .await
+        .unwrap();
+
+    // THEN nothing is deleted
+    assert!(!released);
+}
+
+#[tokio::test]
+async fn debug_obligations_reports_waits() {
+    // GIVEN Create new store
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+
+    // AND readers waiting on two keys
+    let readers: Vec<_> = [1, 2, 2]
+        .into_iter()
+        .map(|key| {
+            let store_copy = store.clone();
+            tokio::spawn(async move { store_copy.notify_read(key).await })
+        })
+        .collect();
+    tokio::task::yield_now().await;
+
+    // WHEN inspecting the obligations a bit later
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let mut awaited = store.debug_obligations().await.unwrap();
+
+    // THEN each awaited key is reported once, with its wait
+    awaited.sort();
+    assert_eq!(awaited.len(), 2);
+    assert_eq!((awaited[0].0, awaited[1].0), (1, 2));
+    assert!(awaited
+        .iter()
+        .all(|(_, wait)| *wait >= std::time::Duration::from_millis(10)));
+
+    // AND nothing is reported once the keys are written
+    store
+        .write_all(vec![(1, "1".to_string()), (2, "2".to_string())])
+        .await
+        .unwrap();
+    for reader in readers {
+        reader.await.unwrap().unwrap();
+    }
+    assert!(store.debug_obligations().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn change_feed_observes_mutations_in_order() {
+    // GIVEN Create new store, and subscribe to its changes
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store
+        .write_all(vec![(0, "before".to_string())])
+        .await
+        .unwrap();
+    let mut feed = store.change_feed();
+
+    // WHEN mutating the store
+    store.write(1, "one".to_string()).await;
+    store
+        .write_all(vec![(2, "two".to_string()), (3, "three".to_string())])
+        .await
+        .unwrap();
+    store.remove(1).await;
+    store.remove_all(vec![2, 3]).await.unwrap();
+
+    // THEN the feed observes the mutations, in order
+    let expected = vec![
+        Change::Write(1, "one".to_string()),
+        Change::Batch(vec![
+            Change::Write(2, "two".to_string()),
+            Change::Write(3, "three".to_string()),
+        ]),
+        Change::Delete(1),
+        Change::Batch(vec![Change::Delete(2), Change::Delete(3)]),
+    ];
+    for change in expected {
+        assert_eq!(feed.recv().await.unwrap(), change);
+    }
+}
+
+#[tokio::test]
+async fn change_feed_reports_lag() {
+    // GIVEN Create new store, and subscribe to its changes
+    let db = rocks::DBMap::<usize, usize>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let mut feed = store.change_feed();
+
+    // WHEN more changes are applied than the feed buffers
+    let count = change_feed::CHANGE_FEED_CAPACITY + 10;
+    for i in 0..count {
+        store.write(i, i).await;
+    }
+    store.read(0).await.unwrap();
+
+    // THEN the subscriber is told how many it missed, and carries on
+    assert_eq!(
+        feed.recv().await.unwrap_err(),
+        change_feed::RecvError::Lagged(10)
+    );
+    assert_eq!(feed.recv().await.unwrap(), Change::Write(10, 10));
+}
+
+#[cfg(feature = "futures-core")]
+#[tokio::test]
+async fn change_feed_stream_ends_with_the_store() {
+    use futures::StreamExt;
+
+    // GIVEN Create new store, and stream its changes
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let mut changes = store.change_feed().into_stream();
+
+    // WHEN mutating the store, then closing it
+    store.write(1, "one".to_string()).await;
+    store.remove(1).await;
+    store.flush_and_close().await.unwrap();
+
+    // THEN the stream yields the mutations in order, then ends
+    assert_eq!(
+        changes.next().await,
+        Some(Ok(Change::Write(1, "one".to_string())))
+    );
+    assert_eq!(changes.next().await, Some(Ok(Change::Delete(1))));
+    assert_eq!(changes.next().await, None);
+}
+
+#[tokio::test]
+async fn read_your_writes_orders_reads_after_writes() {
+    // GIVEN Create new store prioritizing reads
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        prioritize_reads: true,
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+
+    // WHEN reading right after an unacknowledged write
+    store.write(1, "one".to_string()).await;
+    let prioritized = store.read(1).await.unwrap();
+    // AND reading again through a handle ordering its reads after the writes
+    store.write(2, "two".to_string()).await;
+    let ordered = store.read_your_writes().read(2).await.unwrap();
+
+    // THEN the prioritized read overtook the write, but the ordered one did not
+    assert_eq!(prioritized, None);
+    assert_eq!(ordered, Some("two".to_string()));
+    // AND an acknowledged write is visible to prioritized reads once it returns
+    store
+        .write_all(vec![(3, "three".to_string())])
+        .await
+        .unwrap();
+    assert_eq!(store.read(3).await.unwrap(), Some("three".to_string()));
+}
+
+#[tokio::test]
+async fn batch_timeouts_fire_on_stalled_store() {
+    // GIVEN a store running on its own thread
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new_on_dedicated_thread(db);
+
+    // AND its background task stalled, until released
+    let (release, stalled) = std::sync::mpsc::channel::<()>();
+    let stall = move |_: &mut dyn Iterator<Item = (&usize, std::time::Duration)>| {
+        let _ = stalled.recv();
+    };
+    if store
+        .channel
+        .send(StoreCommand::InspectObligations(Box::new(stall)))
+        .await
+        .is_err()
+    {
+        panic!("Failed to stall the store");
+    }
+
+    // WHEN writing and removing with a timeout
+    let timeout = std::time::Duration::from_millis(50);
+    let written = store
+        .write_all_timeout(vec![(1, "1".to_string())], timeout)
+        .await;
+    let removed = store.remove_all_timeout(vec![2], timeout).await;
+
+    // THEN both give up
+    assert_eq!(written, Err(StoreError::Timeout));
+    assert_eq!(removed, Err(StoreError::Timeout));
+
+    // AND the write still goes through once the store is released
+    release.send(()).unwrap();
+    assert_eq!(store.notify_read(1).await.unwrap(), Some("1".to_string()));
+}
+
+#[tokio::test]
+async fn timed_out_writes_stay_in_flight_until_applied() {
+    // GIVEN a store bounding the writes in flight, whose task is not running yet
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        max_in_flight: Some(1),
+        ..Default::default()
+    };
+    let (store, task) = Store::with_task(db, config);
+
+    // AND a write that timed out while queued
+    let timeout = std::time::Duration::from_millis(50);
+    let written = store
+        .write_all_timeout(vec![(1, "1".to_string())], timeout)
+        .await;
+    assert_eq!(written, Err(StoreError::Timeout));
+
+    // WHEN writing again
+    // THEN the queued write still holds its slot
+    assert_eq!(
+        store.write_all(vec![(2, "2".to_string())]).await,
+        Err(StoreError::Overloaded)
+    );
+
+    // AND releases it once it is applied
+    tokio::spawn(task);
+    assert_eq!(store.notify_read(1).await.unwrap(), Some("1".to_string()));
+    let in_flight = store.in_flight.clone().unwrap();
+    while in_flight.available_permits() == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert!(store.write_all(vec![(2, "2".to_string())]).await.is_ok());
+}
+
+#[tokio::test]
+async fn append_only_log_assigns_distinct_sequences() {
+    // GIVEN a log shared by concurrent writers
+    let db = rocks::DBMap::<u64, String>::open(temp_dir(), None, None).unwrap();
+    let log = AppendOnlyLog::new(Store::new(db));
+
+    // WHEN they append concurrently
+    let handles: Vec<_> = (0..10)
+        .map(|writer| {
+            let log = log.clone();
+            tokio::spawn(async move {
+                let mut sequences = Vec::new();
+                for i in 0..10 {
+                    sequences.push(log.append(format!("{writer}-{i}")).await.unwrap());
+                    tokio::task::yield_now().await;
+                }
+                sequences
+            })
+        })
+        .collect();
+    let mut all = Vec::new();
+    for handle in handles {
+        let sequences = handle.await.unwrap();
+        // THEN the sequences of each writer increase
+        assert!(sequences.windows(2).all(|pair| pair[0] < pair[1]));
+        all.extend(sequences);
+    }
+
+    // AND together they are the contiguous sequences from 0
+    all.sort_unstable();
+    assert_eq!(all, (0..100).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn append_fails_on_undecodable_last_key() {
+    // GIVEN a table whose last key is not a sequence
+    let path = temp_dir();
+    let db = rocks::DBMap::<[u8; 9], String>::open(&path, None, None).unwrap();
+    db.insert(&[0xff; 9], &"garbage".to_string()).unwrap();
+    drop(db);
+    let db = rocks::DBMap::<u64, String>::open(&path, None, None).unwrap();
+    let log = AppendOnlyLog::new(Store::new(db));
+
+    // WHEN appending to it
+    let result = log.append("event".to_string()).await;
+
+    // THEN the append fails, rather than restarting the sequences
+    assert!(matches!(result, Err(StoreError::SerializationError(_))));
+    assert!(log.read_from(0, 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn append_only_log_reads_from_sequence() {
+    // GIVEN a log of 20 values
+    let db = rocks::DBMap::<u64, String>::open(temp_dir(), None, None).unwrap();
+    let log = AppendOnlyLog::new(Store::new(db));
+    for i in 0..20 {
+        assert_eq!(log.append(format!("event-{i}")).await.unwrap(), i);
+    }
+
+    // WHEN tailing it from a sequence
+    let entries = log.read_from(5, 10).await.unwrap();
+
+    // THEN the contiguous range of entries is returned
+    let expected: Vec<_> = (5..15).map(|i| (i, format!("event-{i}"))).collect();
+    assert_eq!(entries, expected);
+
+    // AND reading past the end returns the entries left
+    assert_eq!(log.read_from(18, 10).await.unwrap().len(), 2);
+    assert!(log.read_from(20, 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn write_all_duplicate_keys_last_wins() {
+    // GIVEN Create new store, with a pending notify read
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let mut feed = store.change_feed();
+    let store_copy = store.clone();
+    let handle = tokio::spawn(async move { store_copy.notify_read(1).await });
+    tokio::task::yield_now().await;
+
+    // WHEN writing a batch with duplicate keys
+    store
+        .write_all(vec![
+            (1, "first".to_string()),
+            (2, "two".to_string()),
+            (1, "last".to_string()),
+        ])
+        .await
+        .unwrap();
+
+    // THEN the last value of the key is stored, and observed by the notify read
+    assert_eq!(store.read(1).await.unwrap(), Some("last".to_string()));
+    assert_eq!(handle.await.unwrap(), Ok(Some("last".to_string())));
+
+    // AND the batch applies each key once
+    assert_eq!(
+        feed.recv().await.unwrap(),
+        Change::Batch(vec![
+            Change::Write(2, "two".to_string()),
+            Change::Write(1, "last".to_string()),
+        ])
+    );
+
+    // AND so does a traced batch
+    store
+        .write_all_traced(
+            vec![(3, "first".to_string()), (3, "last".to_string())],
+            "request-1",
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        feed.recv().await.unwrap(),
+        Change::Batch(vec![Change::Write(3, "last".to_string())])
+    );
+}
+
+#[tokio::test]
+async fn notify_reads_beyond_max_waiters_are_rejected() {
+    // GIVEN Create new store, bounding the waiters of each key.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        max_waiters_per_key: Some(2),
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+
+    // AND two notify reads pending on a key
+    let waiters: Vec<_> = (0..2)
+        .map(|_| {
+            let store_copy = store.clone();
+            tokio::spawn(async move { store_copy.notify_read(1).await })
+        })
+        .collect();
+    tokio::task::yield_now().await;
+
+    // WHEN registering once more on the key
+    let result = store.notify_read(1).await;
+
+    // THEN the registration is rejected
+    assert_eq!(result, Err(StoreError::TooManyWaiters));
+
+    // AND the other keys can still be awaited
+    let store_copy = store.clone();
+    let other = tokio::spawn(async move { store_copy.notify_read(2).await });
+    tokio::task::yield_now().await;
+
+    // AND the earlier waiters resolve once the key is written
+    store.write(1, "one".to_string()).await;
+    store.write(2, "two".to_string()).await;
+    for waiter in waiters {
+        assert_eq!(waiter.await.unwrap(), Ok(Some("one".to_string())));
+    }
+    assert_eq!(other.await.unwrap(), Ok(Some("two".to_string())));
+}
+
+#[tokio::test]
+async fn abandoned_notify_reads_do_not_count_against_max_waiters() {
+    // GIVEN Create new store, bounding the waiters of each key.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        max_waiters_per_key: Some(2),
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+
+    // AND two notify reads registered on a key, whose callers went away
+    for _ in 0..2 {
+        let store_copy = store.clone();
+        let waiter = tokio::spawn(async move { store_copy.notify_read(1).await });
+        tokio::task::yield_now().await;
+        waiter.abort();
+        assert!(waiter.await.unwrap_err().is_cancelled());
+    }
+
+    // WHEN registering again on the key
+    let store_copy = store.clone();
+    let waiter = tokio::spawn(async move { store_copy.notify_read(1).await });
+    tokio::task::yield_now().await;
+
+    // THEN the registration is accepted, and resolves once the key is written
+    store.write(1, "one".to_string()).await;
+    assert_eq!(waiter.await.unwrap(), Ok(Some("one".to_string())));
+}
+
+#[tokio::test]
+async fn disabled_notify_read_is_rejected() {
+    // GIVEN Create new store without notify reads.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        enable_notify_read: false,
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+
+    // WHEN awaiting keys
+    // THEN the reads are rejected
+    assert_eq!(
+        store.notify_read(1).await,
+        Err(StoreError::NotifyReadDisabled)
+    );
+    assert_eq!(
+        store.notify_read_any(vec![1, 2]).await,
+        Err(StoreError::NotifyReadDisabled)
+    );
+    assert_eq!(
+        store.notify_read_traced(1, "request-1").await,
+        Err(StoreError::NotifyReadDisabled)
+    );
+
+    // AND so are the reads reaching the background task, which registers none of them
+    let (sender, receiver) = oneshot::channel();
+    let command = StoreCommand::NotifyRead(1, sender);
+    if store
+        .channel
+        .send(StoreCommand::Traced(
+            "request-2".to_string(),
+            Box::new(command),
+        ))
+        .await
+        .is_err()
+    {
+        panic!("Failed to send NotifyRead command to store");
+    }
+    assert_eq!(receiver.await.unwrap(), Err(StoreError::NotifyReadDisabled));
+    assert!(store.debug_obligations().await.unwrap().is_empty());
+
+    // AND the writes and reads work normally
+    store.write(1, "one".to_string()).await;
+    store.write_all(vec![(2, "two".to_string())]).await.unwrap();
+    assert_eq!(
+        store.read_all(vec![1, 2, 3]).await.unwrap(),
+        vec![Some("one".to_string()), Some("two".to_string()), None]
+    );
+}
+
+#[tokio::test]
+async fn delete_prefix_removes_only_the_prefix() {
+    // GIVEN Create new store, holding the data of three tenants
+    let db = rocks::DBMap::<(u32, u32), String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    for tenant in [1u32, 2, 3] {
+        let key_values = (0..10).map(|i| ((tenant, i), format!("{tenant}-{i}")));
+        store.write_all(key_values).await.unwrap();
+    }
+
+    // AND a notify read pending within the prefix
+    let store_copy = store.clone();
+    let handle = tokio::spawn(async move { store_copy.notify_read((2, 99)).await });
+    tokio::task::yield_now().await;
+
+    // WHEN deleting the data of a tenant
+    let deleted = store.delete_prefix(2u32).await.unwrap();
+
+    // THEN its keys are gone, and the notify read resolves with None
+    assert_eq!(deleted, 10);
+    assert_eq!(handle.await.unwrap(), Ok(None));
+    let keys = (0..10).map(|i| (2, i));
+    assert!(store
+        .contains_all(keys)
+        .await
+        .unwrap()
+        .into_iter()
+        .all(|found| !found));
+
+    // AND the other tenants are untouched
+    for tenant in [1u32, 3] {
+        let keys = (0..10).map(|i| (tenant, i));
+        assert!(store
+            .contains_all(keys)
+            .await
+            .unwrap()
+            .into_iter()
+            .all(|found| found));
+    }
+}
+
+#[tokio::test]
+async fn dedup_writes_skips_identical_values() {
+    // GIVEN Create new store deduplicating writes, and subscribe to its changes
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let config = StoreConfig {
+        dedup_writes: true,
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+    let mut feed = store.change_feed();
+
+    // WHEN writing the same value twice, then another one
+    store.write(1, "same".to_string()).await;
+    store.write(1, "same".to_string()).await;
+    store.write(1, "other".to_string()).await;
+
+    // THEN the repeated write is not observed
+    assert_eq!(
+        feed.recv().await.unwrap(),
+        Change::Write(1, "same".to_string())
+    );
+    assert_eq!(
+        feed.recv().await.unwrap(),
+        Change::Write(1, "other".to_string())
+    );
+    assert_eq!(store.read(1).await.unwrap(), Some("other".to_string()));
+}
+
+#[tokio::test]
+async fn scan_prefix_cursor_resumes_from_token() {
+    // GIVEN Create new store, with a prefix of 25 entries
+    let db = rocks::DBMap::<(u32, u32), String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let key_values = (0..25).map(|i| ((7, 2 * i), format!("7-{}", 2 * i)));
+    store.write_all(key_values).await.unwrap();
+    store.write((8, 0), "8-0".to_string()).await;
+
+    // AND a scan paused after its first page, as a token
+    let (first, next) = store
+        .scan_prefix_cursor(7u32, Cursor::start(), 10)
+        .await
+        .unwrap();
+    let token = next.unwrap().to_token();
+
+    // WHEN resuming the scan from the token, after intervening writes
+    store.write((7, 1), "7-1".to_string()).await;
+    store.write((7, 51), "7-51".to_string()).await;
+    let mut scanned = first;
+    let mut cursor = Some(Cursor::from_token(&token).unwrap());
+    while let Some(resumed) = cursor {
+        let (page, next) = store.scan_prefix_cursor(7u32, resumed, 10).await.unwrap();
+        scanned.extend(page);
+        cursor = next;
+    }
+
+    // THEN the entries after the cursor are covered exactly once
+    let keys: Vec<_> = scanned.iter().map(|(key, _)| key.1).collect();
+    let expected: Vec<_> = (0..25).map(|i| 2 * i).chain([51]).collect();
+    assert_eq!(keys, expected);
+
+    // AND malformed tokens are rejected
+    assert!(Cursor::<(u32, u32)>::from_token("0g").is_err());
+    assert!(Cursor::<(u32, u32)>::from_token("abc").is_err());
+    assert_eq!(
+        Cursor::<(u32, u32)>::from_token("").unwrap(),
+        Cursor::start()
+    );
+}
+
+#[tokio::test]
+async fn write_nonblocking_fails_on_full_channel() {
+    // GIVEN a store running on its own thread
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new_on_dedicated_thread(db);
+
+    // AND its background task stalled, until released
+    let (release, stalled) = std::sync::mpsc::channel::<()>();
+    let stall = move |_: &mut dyn Iterator<Item = (&usize, std::time::Duration)>| {
+        let _ = stalled.recv();
+    };
+    if store
+        .channel
+        .send(StoreCommand::InspectObligations(Box::new(stall)))
+        .await
+        .is_err()
+    {
+        panic!("Failed to stall the store");
+    }
+
+    // WHEN writing without blocking until the channel is full
+    let mut written = 0;
+    let result = loop {
+        let result = store.write_nonblocking(written, written.to_string());
+        if result.is_err() || written > COMMAND_CHANNEL_CAPACITY {
+            break result;
+        }
+        written += 1;
+    };
+
+    // THEN the write past the capacity of the channel is rejected
+    assert_eq!(result, Err(StoreError::WouldBlock));
+    assert!(written >= COMMAND_CHANNEL_CAPACITY - 1);
+
+    // AND the accepted writes go through once the store is released
+    release.send(()).unwrap();
+    assert_eq!(
+        store.notify_read(written - 1).await.unwrap(),
+        Some((written - 1).to_string())
+    );
+    assert_eq!(store.read(written).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn get_or_populate_runs_a_single_loader() {
+    // GIVEN Create new store.
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let loads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+    // WHEN many callers miss the same key concurrently
+    let handles: Vec<_> = (0..20)
+        .map(|caller| {
+            let store = store.clone();
+            let loads = loads.clone();
+            tokio::spawn(async move {
+                let loader = async move {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    // let the other callers miss in the meantime
+                    for _ in 0..10 {
+                        tokio::task::yield_now().await;
+                    }
+                    Ok(format!("loaded by {caller}"))
+                };
+                store.get_or_populate(1, loader).await
+            })
+        })
+        .collect();
+    let mut values = Vec::new();
+    for handle in handles {
+        values.push(handle.await.unwrap().unwrap());
+    }
+
+    // THEN the loader runs once, and its value is shared by all the callers
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+    assert!(values.iter().all(|value| value == &values[0]));
+    assert_eq!(store.read(1).await.unwrap(), Some(values[0].clone()));
+
+    // AND the key is then served from the store
+    let value = store
+        .get_or_populate(1, async { Err(StoreError::Deadline) })
+        .await
+        .unwrap();
+    assert_eq!(value, values[0]);
+}
+
+#[tokio::test]
+async fn scan_combines_bounds_order_and_limit() {
+    // GIVEN Create new store, with the keys 0, 10, ..., 90
+    let db = rocks::DBMap::<u32, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store
+        .write_all((0..10).map(|i| (10 * i, i.to_string())))
+        .await
+        .unwrap();
+    let scan = |opts: ScanOpts<u32>| {
+        let store = store.clone();
+        async move {
+            let entries = store.scan(opts).await.unwrap();
+            entries.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+        }
+    };
+
+    // WHEN scanning forward, THEN all the keys come in ascending order
+    assert_eq!(
+        scan(ScanOpts::default()).await,
+        (0..10).map(|i| 10 * i).collect::<Vec<_>>()
+    );
+
+    // WHEN scanning backward, THEN they come in descending order
+    let reverse = ScanOpts {
+        reverse: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        scan(reverse).await,
+        (0..10).rev().map(|i| 10 * i).collect::<Vec<_>>()
+    );
+
+    // WHEN scanning a range, THEN its end is excluded, in both directions
+    let bounded = ScanOpts {
+        start: Some(25),
+        end: Some(60),
+        ..Default::default()
+    };
+    assert_eq!(scan(bounded.clone()).await, vec![30, 40, 50]);
+    let bounded_reverse = ScanOpts {
+        reverse: true,
+        ..bounded.clone()
+    };
+    assert_eq!(scan(bounded_reverse).await, vec![50, 40, 30]);
+
+    // WHEN limiting the scans, THEN they stop early, from their direction's start
+    let limited = ScanOpts {
+        limit: Some(2),
+        ..bounded.clone()
+    };
+    assert_eq!(scan(limited.clone()).await, vec![30, 40]);
+    let limited_reverse = ScanOpts {
+        reverse: true,
+        ..limited
+    };
+    assert_eq!(scan(limited_reverse).await, vec![50, 40]);
+    let open_end = ScanOpts {
+        start: Some(70),
+        limit: Some(10),
+        ..Default::default()
+    };
+    assert_eq!(scan(open_end).await, vec![70, 80, 90]);
+
+    // WHEN the start is past the end, THEN the scan is rejected
+    let inverted = ScanOpts {
+        start: Some(60),
+        end: Some(25),
+        ..Default::default()
+    };
+    assert_eq!(
+        store.scan(inverted).await,
+        Err(StoreError::InvalidScanBounds)
+    );
+}
+
+#[tokio::test]
+async fn scan_with_readahead_reads_the_range() {
+    // GIVEN Create new store, with its keys flushed to an SST file
+    let db = rocks::DBMap::<u32, String>::open(temp_dir(), None, None).unwrap();
+    db.multi_insert((0..1000).map(|i| (i, i.to_string())))
+        .unwrap();
+    db.flush().unwrap();
+    let store = Store::new(db);
+
+    // WHEN scanning with a large readahead, in both directions
+    let opts = ScanOpts {
+        start: Some(100),
+        end: Some(900),
+        readahead_size: Some(4 << 20),
+        ..Default::default()
+    };
+    let forward = store.scan(opts.clone()).await.unwrap();
+    let reverse = ScanOpts {
+        reverse: true,
+        ..opts
+    };
+    let backward = store.scan(reverse).await.unwrap();
+
+    // THEN the scans return the range, as without readahead
+    let expected: Vec<_> = (100..900).map(|i| (i, i.to_string())).collect();
+    assert_eq!(forward, expected);
+    assert_eq!(backward, expected.into_iter().rev().collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn get_and_watch_misses_no_update() {
+    // GIVEN Create new store, with a key being written concurrently
+    let db = rocks::DBMap::<u32, u32>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    let writer = store.clone();
+    let handle = tokio::spawn(async move {
+        for i in 0..100 {
+            writer.write(0, i).await;
+            writer.write(1, i).await;
+            tokio::task::yield_now().await;
+        }
+        writer.remove(0).await;
+    });
+    tokio::task::yield_now().await;
+
+    // WHEN reading and watching the key midway
+    let (initial, mut watch) = store.get_and_watch(0).await.unwrap();
+
+    // THEN the watch receives every update following the value read, and only those
+    let mut next = initial.map_or(0, |value| value + 1);
+    while next < 100 {
+        assert_eq!(watch.recv().await, Ok(Some(next)));
+        next += 1;
+    }
+    assert_eq!(watch.recv().await, Ok(None));
+    handle.await.unwrap();
+}
+
+#[cfg(feature = "futures-core")]
+#[tokio::test]
+async fn get_and_watch_stream_ends_with_the_store() {
+    use futures::StreamExt;
+
+    // GIVEN Create new store, and read and stream the updates of a key
+    let db = rocks::DBMap::<usize, String>::open(temp_dir(), None, None).unwrap();
+    let store = Store::new(db);
+    store.write(1, "one".to_string()).await;
+    let (initial, watch) = store.get_and_watch(1).await.unwrap();
+    let mut updates = watch.into_stream();
+    assert_eq!(initial, Some("one".to_string()));
+
+    // WHEN mutating the store, then closing it
+    store.write(2, "two".to_string()).await;
+    store.write(1, "uno".to_string()).await;
+    store.remove(1).await;
+    store.flush_and_close().await.unwrap();
+
+    // THEN the stream yields the updates of the key in order, then ends
+    assert_eq!(updates.next().await, Some(Ok(Some("uno".to_string()))));
+    assert_eq!(updates.next().await, Some(Ok(None)));
+    assert_eq!(updates.next().await, None);
+}
+
+#[tokio::test]
+async fn version_increases_on_mutations_only() {
+    // GIVEN Create new store on a table of its own, tracking its version
+    let path = temp_dir();
+    let rocks = rocks::open_cf(&path, None, &["table"]).unwrap();
+    let db = rocks::DBMap::<u32, String>::reopen(&rocks, Some("table")).unwrap();
+    let config = StoreConfig {
+        track_version: true,
+        ..Default::default()
+    };
+    let store = Store::new_with_config(db, config);
+    let mut version = store.version().await.unwrap();
+
+    // WHEN mutating the store, THEN the version strictly increases each time
+    store.write(1, "one".to_string()).await;
+    let next = store.version().await.unwrap();
+    assert!(next > version);
+    version = next;
+    store
+        .write_all(vec![(2, "two".to_string()), (3, "three".to_string())])
+        .await
+        .unwrap();
+    let next = store.version().await.unwrap();
+    assert!(next > version);
+    version = next;
+    store.remove(1).await;
+    let next = store.version().await.unwrap();
+    assert!(next > version);
+    version = next;
+    store.remove_all(vec![2]).await.unwrap();
+    let next = store.version().await.unwrap();
+    assert!(next > version);
+    version = next;
+
+    // WHEN only reading it, THEN the version stays put
+    store.read(3).await.unwrap();
+    store.read_all(vec![1, 2, 3]).await.unwrap();
+    store.scan(ScanOpts::default()).await.unwrap();
+    assert_eq!(store.version().await, Ok(version));
+
+    // AND the version is persisted along with the mutations
+    let table = rocks::DBMap::<u32, String>::reopen(&rocks, Some("table")).unwrap();
+    assert_eq!(table.stored_version(), Ok(version));
+
+    // AND the version is not tracked unless enabled
+    let untracked = Store::new(rocks::DBMap::<u32, String>::open(temp_dir(), None, None).unwrap());
+    assert!(matches!(
+        untracked.version().await,
+        Err(StoreError::VersionNotTracked(_))
+    ));
+}